@@ -0,0 +1,78 @@
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    board::Board,
+    cell::Cell,
+    heatmap::{Observation, Placements},
+    rules::Rules,
+    ship::ShipSet,
+};
+
+use super::Strategy;
+
+/// Fires wherever the most placements of the ships it hasn't sunk could be
+pub struct Density {
+    rng: ChaCha8Rng,
+    /// Worked out once per set of rules, it's the slow part
+    placements: Option<Placements>,
+}
+
+impl Density {
+    /// How many random layouts to pick the best hiding spot from
+    const LAYOUTS: usize = 32;
+
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            placements: None,
+        }
+    }
+    fn placements(&mut self, rules: &Rules) -> &Placements {
+        // worked out for other rules, so no good for these
+        if self
+            .placements
+            .as_ref()
+            .is_some_and(|known| known.rules() != rules)
+        {
+            self.placements = None;
+        }
+        self.placements
+            .get_or_insert_with(|| Placements::new(rules))
+    }
+}
+
+impl Strategy for Density {
+    /// Rolls a few random layouts and keeps the one sitting on the coldest
+    /// cells, where a player going by the odds would look last
+    fn place(&mut self, rules: &Rules) -> Option<ShipSet> {
+        let seed: u64 = self.rng.gen();
+        let heat = self.placements(rules).heatmap(&Observation::new(rules));
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        (0..Self::LAYOUTS)
+            .filter_map(|_| super::random_fleet(rules, &mut rng))
+            .min_by_key(|set| {
+                set.occupied_cells()
+                    .iter()
+                    .map(|cell| heat.get(*cell))
+                    .sum::<u32>()
+            })
+    }
+    fn aim(&mut self, _defender: usize, board: &Board, shots: usize) -> Vec<Cell> {
+        let mut seen = Observation::of(board);
+        let open = super::open(board);
+        let mut rng = ChaCha8Rng::seed_from_u64(self.rng.gen());
+        let placements = self.placements(board.ships.rules());
+        super::volley(open, shots, |open, picked| {
+            // the rest of a volley goes where the first shots didn't
+            for cell in picked {
+                seen.misses.insert(*cell);
+            }
+            let heat = placements.heatmap(&seen);
+            let best = |cell: &Cell| (heat.targeted(*cell), heat.get(*cell));
+            let top = open.cells().map(|cell| best(&cell)).max()?;
+            let ties: Vec<Cell> = open.cells().filter(|cell| best(cell) == top).collect();
+            ties.choose(&mut rng).copied()
+        })
+    }
+}
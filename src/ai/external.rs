@@ -0,0 +1,431 @@
+//! Bots in their own process, in whatever language, talking over stdin and
+//! stdout a line at a time.
+//!
+//! Cells are written like `B7`, players are numbered from 1, and words are
+//! separated by spaces. When the game starts, the bot is told the rules:
+//!
+//! ```text
+//! battleship 1                 protocol version
+//! seed 1234                    to play the same way every time, if it likes
+//! geometry flat                or torus
+//! placement touching           or no-touching
+//! diagonals no                 or yes
+//! land C3 C4 D4                only if there is any
+//! ship 0,0;0,1;0,2 Destroyer   one per ship of the fleet, in order: its shape
+//!                              facing down, as x,y offsets from the bow
+//! ```
+//!
+//! Then it's asked for its fleet with `place`, and answers with one line per
+//! ship in the same form as a [layout list](crate::layout), like
+//! `Destroyer B2 down`, followed by `end`.
+//!
+//! On its turn it hears about every shot at the player it's aiming at that it
+//! hasn't heard about yet, then gets asked for its shots:
+//!
+//! ```text
+//! shot 2 B7 miss
+//! shot 2 C7 hit Destroyer      the ship's only named if the rules say so
+//! shot 2 D7 sunk Destroyer
+//! fire 2 3                     three shots at player 2, please
+//! ```
+//!
+//! and answers with that many cells on one line, like `A1 E5 J10`. Every
+//! answer has to come within the time limit. Anything late, malformed or
+//! impossible forfeits the game, and so does quitting. Once the game's over
+//! it's sent `quit`.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bitboard::Bits,
+    board::{Board, Report, Shot},
+    cell::Cell,
+    error::Error,
+    layout,
+    rules::{Geometry, Placement, Rules},
+    ship::ShipSet,
+};
+
+use super::Strategy;
+
+const PROTOCOL: u32 = 1;
+
+pub struct External {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    limit: Duration,
+    seed: u64,
+    /// Whether it's been told the rules yet
+    started: bool,
+    /// The shots it's been told about, per player
+    told: Vec<Bits>,
+    /// Why it forfeited, once it has
+    trouble: Option<String>,
+}
+
+impl External {
+    /// Starts `command`, a program and its arguments split on spaces. Its
+    /// stderr is thrown away unless `stderr` is set, so it can't scribble
+    /// over the screen.
+    pub fn spawn(command: &str, limit: Duration, seed: u64, stderr: bool) -> Result<Self, Error> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| Error::Usage("no bot command given".to_string()))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(if stderr {
+                Stdio::inherit()
+            } else {
+                Stdio::null()
+            })
+            .spawn()
+            .map_err(|e| Error::Usage(format!("couldn't start {program}: {e}")))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(Error::Usage(format!("couldn't talk to {program}")));
+        };
+        // blocking reads go on their own thread, so replies can time out
+        let (send, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if send.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+            limit,
+            seed,
+            started: false,
+            told: Vec::new(),
+            trouble: None,
+        })
+    }
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{line}")
+            .and_then(|()| self.stdin.flush())
+            .map_err(|e| format!("couldn't be reached ({e})"))
+    }
+    /// The next line, if it comes before `deadline`
+    fn read(&mut self, deadline: Instant) -> Result<String, String> {
+        match self
+            .lines
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => {
+                Err(format!("took longer than {:.1?} to answer", self.limit))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err("quit".to_string()),
+        }
+    }
+    /// Tells the bot the rules, the first time it's needed
+    fn start(&mut self, rules: &Rules) -> Result<(), String> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        self.send(&format!("battleship {PROTOCOL}"))?;
+        self.send(&format!("seed {}", self.seed))?;
+        self.send(match rules.geometry {
+            Geometry::Flat => "geometry flat",
+            Geometry::Torus => "geometry torus",
+        })?;
+        self.send(match rules.placement {
+            Placement::Touching => "placement touching",
+            Placement::NoTouching => "placement no-touching",
+        })?;
+        self.send(if rules.diagonals {
+            "diagonals yes"
+        } else {
+            "diagonals no"
+        })?;
+        let land = rules.terrain.land_cells();
+        if !land.is_empty() {
+            let land: Vec<String> = land.iter().map(ToString::to_string).collect();
+            self.send(&format!("land {}", land.join(" ")))?;
+        }
+        for kind in rules.fleet.ships() {
+            let shape: Vec<String> = kind
+                .shape()
+                .cells()
+                .iter()
+                .map(|(x, y)| format!("{x},{y}"))
+                .collect();
+            self.send(&format!("ship {} {kind}", shape.join(";")))?;
+        }
+        Ok(())
+    }
+    fn try_place(&mut self, rules: &Rules) -> Result<ShipSet, String> {
+        self.start(rules)?;
+        self.send("place")?;
+        // the whole fleet has to come within the time limit, not each line
+        let deadline = Instant::now() + self.limit;
+        let mut text = String::new();
+        let mut lines = 0;
+        loop {
+            let line = self.read(deadline)?;
+            if line.trim() == "end" {
+                break;
+            }
+            lines += 1;
+            if lines > rules.fleet.len() {
+                return Err(format!(
+                    "sent more than {} ships without an end",
+                    rules.fleet.len()
+                ));
+            }
+            text.push_str(&line);
+            text.push('\n');
+        }
+        layout::parse(&text, rules).map_err(|e| e.to_string())
+    }
+    fn try_aim(
+        &mut self,
+        defender: usize,
+        board: &Board,
+        shots: usize,
+    ) -> Result<Vec<Cell>, String> {
+        self.start(board.ships.rules())?;
+        if self.told.len() <= defender {
+            self.told.resize(defender + 1, Bits::EMPTY);
+        }
+        for cell in Bits::ALL.cells() {
+            let shot = board.shot(&cell);
+            if shot == Shot::Empty || self.told[defender].contains(cell) {
+                continue;
+            }
+            self.told[defender].insert(cell);
+            let what = match board.report(&shot) {
+                Report::Sunk(kind) => format!("sunk {kind}"),
+                Report::Hit(Some(kind)) => format!("hit {kind}"),
+                Report::Hit(None) => "hit".to_string(),
+                Report::Miss | Report::Nothing => "miss".to_string(),
+            };
+            self.send(&format!("shot {} {cell} {what}", defender + 1))?;
+        }
+        self.send(&format!("fire {} {shots}", defender + 1))?;
+        let line = self.read(Instant::now() + self.limit)?;
+        let cells = line
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<Cell>, _>>()
+            .map_err(|e| format!("sent {line:?}: {e}"))?;
+        if cells.len() != shots {
+            return Err(format!("sent {} shots instead of {shots}", cells.len()));
+        }
+        for (i, cell) in cells.iter().enumerate() {
+            if !board.can_fire_at(cell) || cells[..i].contains(cell) {
+                return Err(format!("can't fire at {cell}"));
+            }
+        }
+        Ok(cells)
+    }
+}
+
+impl Strategy for External {
+    fn place(&mut self, rules: &Rules) -> Option<ShipSet> {
+        if self.trouble.is_some() {
+            return None;
+        }
+        self.try_place(rules)
+            .map_err(|trouble| self.trouble = Some(trouble))
+            .ok()
+    }
+    fn aim(&mut self, defender: usize, board: &Board, shots: usize) -> Vec<Cell> {
+        if self.trouble.is_some() {
+            return Vec::new();
+        }
+        self.try_aim(defender, board, shots)
+            .unwrap_or_else(|trouble| {
+                self.trouble = Some(trouble);
+                Vec::new()
+            })
+    }
+    fn trouble(&self) -> Option<&str> {
+        self.trouble.as_deref()
+    }
+}
+
+impl Drop for External {
+    fn drop(&mut self) {
+        // it's had its chance to leave politely
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    /// Writes down everything it hears, places whatever's in `fleet`, and
+    /// answers each `fire` with whatever's in `fire`, after `wait` seconds
+    const SCRIPT: &str = r#"
+while read -r line; do
+    echo "$line" >> "$1/heard"
+    case "$line" in
+        place) cat "$1/fleet"; echo end ;;
+        fire*) sleep "$2"; cat "$1/fire" ;;
+        quit) exit ;;
+    esac
+done
+"#;
+
+    /// A bot running [`SCRIPT`] in a directory of its own
+    struct Scripted {
+        dir: PathBuf,
+        bot: External,
+    }
+
+    impl Scripted {
+        fn new(name: &str, fleet: &str, fire: &str, wait: &str, limit: Duration) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("battleship-bot-{name}-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("bot.sh"), SCRIPT).unwrap();
+            std::fs::write(dir.join("fleet"), fleet).unwrap();
+            std::fs::write(dir.join("fire"), format!("{fire}\n")).unwrap();
+            let command = format!(
+                "sh {} {} {wait}",
+                dir.join("bot.sh").display(),
+                dir.display()
+            );
+            let bot = External::spawn(&command, limit, 42, false).unwrap();
+            Self { dir, bot }
+        }
+        fn heard(&self) -> String {
+            std::fs::read_to_string(self.dir.join("heard")).unwrap_or_default()
+        }
+    }
+
+    impl Drop for Scripted {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn fleet(rules: &Rules) -> ShipSet {
+        crate::ai::random_fleet(rules, &mut ChaCha8Rng::seed_from_u64(3)).unwrap()
+    }
+
+    fn board(rules: &Rules) -> Board {
+        Board::new(fleet(rules))
+    }
+
+    #[test]
+    fn tells_the_rules_and_takes_a_fleet() {
+        let rules = Rules::default();
+        let ships = fleet(&rules);
+        let mut bot = Scripted::new(
+            "place",
+            &layout::to_list(&ships),
+            "A1",
+            "0",
+            Duration::from_secs(5),
+        );
+        // straight ships come back unmirrored, which covers the same cells
+        let placed = bot.bot.place(&rules).unwrap();
+        assert_eq!(layout::to_list(&placed), layout::to_list(&ships));
+        assert_eq!(bot.bot.trouble(), None);
+        let heard = bot.heard();
+        let mut expected = vec![
+            "battleship 1".to_string(),
+            "seed 42".to_string(),
+            "geometry flat".to_string(),
+            "placement touching".to_string(),
+            "diagonals no".to_string(),
+        ];
+        for kind in rules.fleet.ships() {
+            let length = kind.length();
+            let shape: Vec<String> = (0..length).map(|y| format!("0,{y}")).collect();
+            expected.push(format!("ship {} {kind}", shape.join(";")));
+        }
+        expected.push("place".to_string());
+        assert_eq!(heard.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn fires_where_it_says() {
+        let rules = Rules::default();
+        let mut target = board(&rules);
+        target.fire(&"B2".parse().unwrap());
+        let mut bot = Scripted::new("aim", "", "A1 C3", "0", Duration::from_secs(5));
+        let cells = bot.bot.aim(1, &target, 2);
+        assert_eq!(cells, ["A1".parse().unwrap(), "C3".parse().unwrap()]);
+        assert_eq!(bot.bot.trouble(), None);
+        // told about the shot already taken, then asked
+        let heard = bot.heard();
+        let mut lines = heard.lines().skip_while(|line| !line.starts_with("shot"));
+        assert!(lines
+            .next()
+            .is_some_and(|line| line.starts_with("shot 2 B2 ")));
+        assert_eq!(lines.next(), Some("fire 2 2"));
+    }
+
+    #[test]
+    fn malformed_replies_forfeit() {
+        let rules = Rules::default();
+        let mut bot = Scripted::new("malformed", "", "A1 Z9", "0", Duration::from_secs(5));
+        assert!(bot.bot.aim(1, &board(&rules), 2).is_empty());
+        assert!(bot
+            .bot
+            .trouble()
+            .is_some_and(|trouble| trouble.contains("Z9")));
+        // and it isn't asked again
+        assert!(bot.bot.aim(1, &board(&rules), 2).is_empty());
+    }
+
+    #[test]
+    fn firing_twice_at_a_cell_forfeits() {
+        let rules = Rules::default();
+        let mut bot = Scripted::new("twice", "", "D4 D4", "0", Duration::from_secs(5));
+        assert!(bot.bot.aim(1, &board(&rules), 2).is_empty());
+        assert_eq!(bot.bot.trouble(), Some("can't fire at D4"));
+    }
+
+    #[test]
+    fn bad_fleets_forfeit() {
+        let rules = Rules::default();
+        let mut bot = Scripted::new(
+            "fleet",
+            "Nonsense A1 down\n",
+            "A1",
+            "0",
+            Duration::from_secs(5),
+        );
+        assert_eq!(bot.bot.place(&rules), None);
+        assert!(bot.bot.trouble().is_some());
+    }
+
+    #[test]
+    fn late_replies_forfeit() {
+        let rules = Rules::default();
+        let mut bot = Scripted::new("late", "", "A1", "2", Duration::from_millis(200));
+        let asked = Instant::now();
+        assert!(bot.bot.aim(1, &board(&rules), 1).is_empty());
+        assert!(asked.elapsed() < Duration::from_secs(1));
+        assert!(bot
+            .bot
+            .trouble()
+            .is_some_and(|trouble| trouble.starts_with("took longer")));
+    }
+}
@@ -0,0 +1,86 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    bitboard::Bits,
+    board::Board,
+    cell::Cell,
+    heatmap::Observation,
+    rules::{Geometry, Rules},
+    ship::{ShipSet, ShipType},
+};
+
+use super::Strategy;
+
+/// Searches a checkerboard spaced out by the smallest ship left, and once
+/// something's hit, fires around it until it sinks
+pub struct HuntTarget {
+    rng: ChaCha8Rng,
+}
+
+impl HuntTarget {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+    fn pick(&mut self, seen: &Observation, open: Bits, geometry: Geometry) -> Option<Cell> {
+        const SIDES: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+        let mut next_to = Bits::EMPTY;
+        let mut in_line = Bits::EMPTY;
+        for hit in seen.hits.cells() {
+            for (dx, dy) in SIDES {
+                let Some(near) = geometry
+                    .offset(hit, dx, dy)
+                    .filter(|near| open.contains(*near))
+                else {
+                    continue;
+                };
+                next_to.insert(near);
+                // another hit on the far side means this is probably the same ship
+                if geometry
+                    .offset(hit, -dx, -dy)
+                    .is_some_and(|behind| seen.hits.contains(behind))
+                {
+                    in_line.insert(near);
+                }
+            }
+        }
+        if !in_line.is_empty() {
+            return super::any(in_line, &mut self.rng);
+        }
+        if !next_to.is_empty() {
+            return super::any(next_to, &mut self.rng);
+        }
+        // no ship can hide between the squares of this checkerboard
+        let spacing = seen
+            .remaining
+            .iter()
+            .map(ShipType::length)
+            .min()
+            .unwrap_or(1)
+            .max(1);
+        let parity: Bits = open
+            .cells()
+            .filter(|cell| (cell.x() + cell.y()) % spacing == 0)
+            .collect();
+        if parity.is_empty() {
+            super::any(open, &mut self.rng)
+        } else {
+            super::any(parity, &mut self.rng)
+        }
+    }
+}
+
+impl Strategy for HuntTarget {
+    fn place(&mut self, rules: &Rules) -> Option<ShipSet> {
+        super::random_fleet(rules, &mut self.rng)
+    }
+    fn aim(&mut self, _defender: usize, board: &Board, shots: usize) -> Vec<Cell> {
+        let geometry = board.ships.rules().geometry;
+        let seen = Observation::of(board);
+        super::volley(super::open(board), shots, |open, _picked| {
+            self.pick(&seen, open, geometry)
+        })
+    }
+}
@@ -0,0 +1,170 @@
+//! Computer opponents. Every one of them only goes by what a human in their
+//! place would be told, through [`Board::report`].
+
+mod density;
+pub mod external;
+mod hunt;
+mod random;
+
+pub use density::Density;
+pub use external::External;
+pub use hunt::HuntTarget;
+pub use random::RandomShots;
+
+use std::time::Duration;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    bitboard::Bits,
+    board::Board,
+    cell::Cell,
+    error::Error,
+    rules::Rules,
+    ship::{ShipSet, ShipSetBuilder},
+};
+
+/// How a computer player places its fleet and picks its shots
+pub trait Strategy: Send {
+    /// Lays out a fleet, or `None` if it doesn't fit under `rules`
+    fn place(&mut self, rules: &Rules) -> Option<ShipSet>;
+    /// Picks `shots` different cells of `board`, which is player
+    /// `defender`'s, to fire at. Fewer if there aren't that many left, and
+    /// none at all to give up.
+    fn aim(&mut self, defender: usize, board: &Board, shots: usize) -> Vec<Cell>;
+    /// Why this player gave up, if they did and there's more to say about it
+    /// than that
+    fn trouble(&self) -> Option<&str> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Difficulty {
+    /// Fires anywhere it hasn't yet
+    Easy,
+    /// Searches a checkerboard, then finishes off whatever it hits
+    #[default]
+    Normal,
+    /// Fires wherever ships are most likely to be, and hides its own fleet
+    /// where yours are least likely to look
+    Hard,
+}
+
+impl Difficulty {
+    /// A fresh player of this difficulty, always playing the same way for
+    /// the same seed
+    pub fn strategy(self, seed: u64) -> Box<dyn Strategy> {
+        match self {
+            Self::Easy => Box::new(RandomShots::new(seed)),
+            Self::Normal => Box::new(HuntTarget::new(seed)),
+            Self::Hard => Box::new(Density::new(seed)),
+        }
+    }
+}
+
+/// Who's behind a computer player: one of ours, or a bot program
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Opponent {
+    Builtin(Difficulty),
+    /// A command to start, talked to as in [`external`]
+    External {
+        command: String,
+        limit: Duration,
+    },
+}
+
+impl Opponent {
+    /// How long bots get to answer, unless they're told otherwise
+    pub const TIME_LIMIT: Duration = Duration::from_secs(1);
+
+    /// A fresh player, always playing the same way for the same seed if it's
+    /// one of ours. Bots only get their stderr shown with `show_stderr`.
+    pub fn strategy(&self, seed: u64, show_stderr: bool) -> Result<Box<dyn Strategy>, Error> {
+        match self {
+            Self::Builtin(difficulty) => Ok(difficulty.strategy(seed)),
+            Self::External { command, limit } => Ok(Box::new(External::spawn(
+                command,
+                *limit,
+                seed,
+                show_stderr,
+            )?)),
+        }
+    }
+}
+
+impl Default for Opponent {
+    fn default() -> Self {
+        Self::Builtin(Difficulty::default())
+    }
+}
+
+impl std::fmt::Display for Opponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Builtin(difficulty) => write!(f, "{difficulty}"),
+            Self::External { command, .. } => f.write_str(command),
+        }
+    }
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Easy => f.write_str("Easy"),
+            Self::Normal => f.write_str("Normal"),
+            Self::Hard => f.write_str("Hard"),
+        }
+    }
+}
+
+impl std::str::FromStr for Difficulty {
+    type Err = String;
+
+    /// Difficulty names, or what the strategy behind them is called
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "easy" | "random" => Ok(Self::Easy),
+            "normal" | "hunt" => Ok(Self::Normal),
+            "hard" | "density" => Ok(Self::Hard),
+            _ => Err(format!("{name} isn't a strategy, try easy, normal or hard")),
+        }
+    }
+}
+
+/// Any valid layout, picked with `rng`
+pub fn random_fleet(rules: &Rules, rng: &mut impl Rng) -> Option<ShipSet> {
+    let mut builder = ShipSetBuilder::new(rules);
+    if !builder.randomize(rng.gen()) {
+        return None;
+    }
+    builder.build().ok()
+}
+
+/// Every cell of `defender` that can still be fired at
+fn open(defender: &Board) -> Bits {
+    defender.targets().into_iter().collect()
+}
+
+/// Picks `shots` cells with `pick`, which never gets handed a cell it's
+/// already picked
+fn volley(
+    mut open: Bits,
+    shots: usize,
+    mut pick: impl FnMut(Bits, &[Cell]) -> Option<Cell>,
+) -> Vec<Cell> {
+    let mut out = Vec::with_capacity(shots);
+    while out.len() < shots && !open.is_empty() {
+        let Some(cell) = pick(open, &out) else {
+            break;
+        };
+        open.remove(cell);
+        out.push(cell);
+    }
+    out
+}
+
+fn any(cells: Bits, rng: &mut impl Rng) -> Option<Cell> {
+    let cells: Vec<Cell> = cells.cells().collect();
+    cells.choose(rng).copied()
+}
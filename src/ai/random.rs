@@ -0,0 +1,30 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{board::Board, cell::Cell, rules::Rules, ship::ShipSet};
+
+use super::Strategy;
+
+/// Fires anywhere it hasn't yet
+pub struct RandomShots {
+    rng: ChaCha8Rng,
+}
+
+impl RandomShots {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for RandomShots {
+    fn place(&mut self, rules: &Rules) -> Option<ShipSet> {
+        super::random_fleet(rules, &mut self.rng)
+    }
+    fn aim(&mut self, _defender: usize, board: &Board, shots: usize) -> Vec<Cell> {
+        super::volley(super::open(board), shots, |open, _picked| {
+            super::any(open, &mut self.rng)
+        })
+    }
+}
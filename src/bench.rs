@@ -0,0 +1,77 @@
+//! `battleship bench [games]`: plays the same random games out on a
+//! [`Board`] and on a [`BitBoard`], and says how long each took.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{ai::random_fleet, bitboard::BitBoard, board::Board, cell::Cell, rules::Rules};
+
+pub fn run(games: usize) {
+    let rules = Rules::default();
+    // set up every game first, so only the shooting is timed
+    let setups: Vec<(Board, Vec<Cell>)> = (0..games as u64)
+        .filter_map(|seed| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let board = Board::new(random_fleet(&rules, &mut rng)?);
+            let mut order = board.targets();
+            order.shuffle(&mut rng);
+            Some((board, order))
+        })
+        .collect();
+    let (shots, board_time) = time(|| {
+        let mut shots = 0;
+        for (board, order) in &setups {
+            let mut board = board.clone();
+            for cell in order {
+                if board.lost() {
+                    break;
+                }
+                black_box(board.fire(cell));
+                shots += 1;
+            }
+        }
+        shots
+    });
+    let bit_boards: Vec<(BitBoard, &[Cell])> = setups
+        .iter()
+        .map(|(board, order)| (BitBoard::from(board), order.as_slice()))
+        .collect();
+    let (bit_shots, bit_time) = time(|| {
+        let mut shots = 0;
+        for (board, order) in &bit_boards {
+            let mut board = board.clone();
+            for cell in *order {
+                if board.lost() {
+                    break;
+                }
+                black_box(board.fire(*cell));
+                shots += 1;
+            }
+        }
+        shots
+    });
+    println!("{} games", setups.len());
+    report("Board", shots, board_time);
+    report("BitBoard", bit_shots, bit_time);
+    println!(
+        "BitBoard is {:.1}x faster",
+        board_time.as_secs_f64() / bit_time.as_secs_f64().max(f64::EPSILON)
+    );
+}
+
+fn time(f: impl FnOnce() -> usize) -> (usize, Duration) {
+    let start = Instant::now();
+    let out = f();
+    (out, start.elapsed())
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn report(name: &str, shots: usize, took: Duration) {
+    let per_second = shots as f64 / took.as_secs_f64().max(f64::EPSILON);
+    println!("{name: <9} {shots} shots in {took:.2?}, {per_second:.0} shots/s");
+}
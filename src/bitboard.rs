@@ -0,0 +1,362 @@
+//! Boards packed into bits, for simulating a lot of games quickly.
+//!
+//! These only know which cells are which, not what's on them, so they're
+//! for bots and tournaments to play with. Real games go through [`Board`].
+
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+use crate::{
+    board::{Board, Shot},
+    cell::Cell,
+    rules::Geometry,
+    ship::{ShipSet, ShipState},
+};
+
+/// Any set of cells, one bit each, `x + 10 * y`
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct Bits(u128);
+
+impl Bits {
+    pub const EMPTY: Self = Self(0);
+    /// The whole board
+    pub const ALL: Self = Self((1 << 100) - 1);
+
+    pub const fn cell(cell: Cell) -> Self {
+        Self(1 << (cell.x() + 10 * cell.y()))
+    }
+    /// The cells `ship` covers
+    pub fn ship(ship: &ShipState, geometry: Geometry) -> Self {
+        ship.occupies(geometry).into_iter().collect()
+    }
+    pub const fn contains(self, cell: Cell) -> bool {
+        self.0 & Self::cell(cell).0 != 0
+    }
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+    /// Returns `true` if every cell of `self` is also in `other`
+    pub const fn is_subset(self, other: Self) -> bool {
+        self.0 & !other.0 == 0
+    }
+    pub const fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+    pub fn insert(&mut self, cell: Cell) {
+        *self |= Self::cell(cell);
+    }
+    pub fn remove(&mut self, cell: Cell) {
+        self.0 &= !Self::cell(cell).0;
+    }
+    /// Every cell in the set, in `x + 10 * y` order
+    pub fn cells(self) -> impl Iterator<Item = Cell> {
+        let mut left = self.0;
+        std::iter::from_fn(move || {
+            if left == 0 {
+                return None;
+            }
+            let bit = left.trailing_zeros() as usize;
+            // clear the lowest bit
+            left &= left - 1;
+            Some(Cell::new(bit % 10, bit / 10))
+        })
+    }
+}
+
+impl FromIterator<Cell> for Bits {
+    fn from_iter<T: IntoIterator<Item = Cell>>(cells: T) -> Self {
+        let mut out = Self::EMPTY;
+        for cell in cells {
+            out.insert(cell);
+        }
+        out
+    }
+}
+
+impl BitOr for Bits {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Bits {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for Bits {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+// only ever the cells of the board, never the 28 bits past it
+impl Not for Bits {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0 & Self::ALL.0)
+    }
+}
+
+/// Where every ship of a fleet is, in fleet order
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct BitFleet {
+    ships: Vec<Bits>,
+    occupied: Bits,
+}
+
+impl BitFleet {
+    pub const fn new() -> Self {
+        Self {
+            ships: Vec::new(),
+            occupied: Bits::EMPTY,
+        }
+    }
+    /// Adds the next ship of the fleet. Returns `false` if it'd overlap one
+    /// that's already there, in which case nothing changes.
+    pub fn place(&mut self, ship: Bits) -> bool {
+        if self.overlaps(ship) {
+            return false;
+        }
+        self.ships.push(ship);
+        self.occupied |= ship;
+        true
+    }
+    /// Returns `true` if a ship covering `cells` would overlap the fleet
+    pub const fn overlaps(&self, cells: Bits) -> bool {
+        self.occupied.intersects(cells)
+    }
+    /// Which ship of the fleet is on `cell`, if any
+    pub fn index_in(&self, cell: Cell) -> Option<usize> {
+        if !self.occupied.contains(cell) {
+            return None;
+        }
+        self.ships.iter().position(|ship| ship.contains(cell))
+    }
+    pub fn ships(&self) -> &[Bits] {
+        &self.ships
+    }
+    /// Every cell with a ship on it
+    pub const fn occupied(&self) -> Bits {
+        self.occupied
+    }
+}
+
+impl From<&ShipSet> for BitFleet {
+    fn from(set: &ShipSet) -> Self {
+        let geometry = set.rules().geometry;
+        let mut out = Self::new();
+        for ship in set.ships() {
+            out.place(Bits::ship(ship, geometry));
+        }
+        out
+    }
+}
+
+/// What came of firing at a [`BitBoard`]. Ships are fleet indices.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Fired {
+    Miss,
+    Hit(usize),
+    Sunk(usize),
+}
+
+/// A fleet and everything that's been fired at it
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct BitBoard {
+    fleet: BitFleet,
+    land: Bits,
+    hits: Bits,
+    misses: Bits,
+}
+
+impl BitBoard {
+    pub const fn new(fleet: BitFleet, land: Bits) -> Self {
+        Self {
+            fleet,
+            land,
+            hits: Bits::EMPTY,
+            misses: Bits::EMPTY,
+        }
+    }
+    /// Returns `None` if `cell` is land or has already been fired at, in
+    /// which case nothing changes. Unlike [`Board::fire`], nothing around a
+    /// sunk ship gets marked.
+    pub fn fire(&mut self, cell: Cell) -> Option<Fired> {
+        let bit = Bits::cell(cell);
+        if (self.hits | self.misses | self.land).intersects(bit) {
+            return None;
+        }
+        if !self.fleet.overlaps(bit) {
+            self.misses |= bit;
+            return Some(Fired::Miss);
+        }
+        self.hits |= bit;
+        let index = self.fleet.index_in(cell)?;
+        if self.is_sunk(index) {
+            Some(Fired::Sunk(index))
+        } else {
+            Some(Fired::Hit(index))
+        }
+    }
+    pub fn is_sunk(&self, index: usize) -> bool {
+        self.fleet
+            .ships()
+            .get(index)
+            .is_some_and(|ship| ship.is_subset(self.hits))
+    }
+    pub const fn lost(&self) -> bool {
+        self.fleet.occupied().is_subset(self.hits)
+    }
+    pub const fn hits(&self) -> Bits {
+        self.hits
+    }
+    pub const fn misses(&self) -> Bits {
+        self.misses
+    }
+    /// Every cell that can still be fired at
+    pub fn targets(&self) -> Bits {
+        !(self.hits | self.misses | self.land)
+    }
+    pub const fn fleet(&self) -> &BitFleet {
+        &self.fleet
+    }
+    /// The same shots fired at a real board of `ships`. Returns `None` if
+    /// `ships` isn't the fleet on this board.
+    pub fn to_board(&self, ships: ShipSet) -> Option<Board> {
+        if BitFleet::from(&ships) != self.fleet {
+            return None;
+        }
+        let mut board = Board::new(ships);
+        for cell in (self.hits | self.misses).cells() {
+            // no-touching boards may have marked it already
+            board.fire(&cell);
+        }
+        Some(board)
+    }
+}
+
+impl From<&Board> for BitBoard {
+    fn from(board: &Board) -> Self {
+        let land = board
+            .ships
+            .rules()
+            .terrain
+            .land_cells()
+            .into_iter()
+            .collect();
+        let mut out = Self::new(BitFleet::from(&board.ships), land);
+        for cell in Bits::ALL.cells() {
+            match board.shot(&cell) {
+                Shot::Hit(_) => out.hits.insert(cell),
+                Shot::Miss => out.misses.insert(cell),
+                Shot::Empty => {}
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{seq::SliceRandom, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::rules::Rules;
+
+    fn fleet(seed: u64) -> ShipSet {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        crate::ai::random_fleet(&Rules::default(), &mut rng).unwrap()
+    }
+
+    /// Every cell of the board, in an order that depends on `seed`
+    fn shuffled(seed: u64) -> Vec<Cell> {
+        let mut cells: Vec<Cell> = Bits::ALL.cells().collect();
+        cells.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+        cells
+    }
+
+    #[test]
+    fn round_trips_through_a_board() {
+        let ships = fleet(1);
+        let mut board = Board::new(ships.clone());
+        for cell in shuffled(2).into_iter().take(40) {
+            board.fire(&cell);
+        }
+        let bits = BitBoard::from(&board);
+        assert_eq!(bits.fleet(), &BitFleet::from(&ships));
+        let back = bits.to_board(ships).unwrap();
+        for cell in Bits::ALL.cells() {
+            assert_eq!(back.shot(&cell), board.shot(&cell), "at {cell}");
+        }
+        assert_eq!(BitBoard::from(&back), bits);
+    }
+
+    #[test]
+    fn to_board_wants_the_same_fleet() {
+        let bits = BitBoard::from(&Board::new(fleet(1)));
+        assert!(bits.to_board(fleet(3)).is_none());
+    }
+
+    #[test]
+    fn fires_like_a_board() {
+        let ships = fleet(4);
+        let mut board = Board::new(ships.clone());
+        let mut bits = BitBoard::from(&board);
+        let order: Vec<&ShipState> = ships.ships().collect();
+        for cell in shuffled(5) {
+            let fired = bits.fire(cell);
+            let shot = board.fire(&cell);
+            match (fired, shot) {
+                (Some(Fired::Miss), Some(Shot::Miss)) => {}
+                (Some(Fired::Hit(index)), Some(Shot::Hit(ship))) => {
+                    assert_eq!(order[index], ship.as_ref());
+                    assert!(!board.sunk(&ship));
+                }
+                (Some(Fired::Sunk(index)), Some(Shot::Hit(ship))) => {
+                    assert_eq!(order[index], ship.as_ref());
+                    assert!(board.sunk(&ship));
+                    assert!(bits.is_sunk(index));
+                }
+                other => panic!("{cell} went differently: {other:?}"),
+            }
+            assert_eq!(bits.lost(), board.lost());
+            assert_eq!(bits.targets(), board.targets().into_iter().collect());
+            assert_eq!(
+                bits.hits().len() + bits.misses().len(),
+                100 - board.unfired()
+            );
+            // nothing can be fired at twice
+            assert_eq!(bits.fire(cell), None);
+        }
+        assert!(bits.lost());
+        assert_eq!(bits.hits(), bits.fleet().occupied());
+    }
+
+    #[test]
+    fn overlaps_only_the_fleet() {
+        let ships = fleet(6);
+        let bits = BitFleet::from(&ships);
+        let geometry = ships.rules().geometry;
+        for (index, ship) in ships.ships().enumerate() {
+            let cells = Bits::ship(ship, geometry);
+            assert_eq!(cells.len(), ship.length());
+            assert!(bits.overlaps(cells));
+            for cell in cells.cells() {
+                assert_eq!(bits.index_in(cell), Some(index));
+            }
+        }
+        let water = !bits.occupied();
+        assert!(!bits.overlaps(water));
+        assert_eq!(water.len() + bits.occupied().len(), 100);
+    }
+}
@@ -2,37 +2,178 @@ use std::sync::Arc;
 
 use crate::{
     cell::Cell,
-    ship::{ShipSet, ShipState},
+    clock::Clock,
+    rules::{Disclosure, Placement},
+    ship::{Motion, ShipRotation, ShipSet, ShipState, ShipType},
+    weapon::{Arsenal, Weapon},
 };
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "SavedBoard", into = "SavedBoard")
+)]
 pub struct Board {
     locals: RawBoard,
     pub ships: ShipSet,
+    /// Special weapons the owner of this board has left to fire
+    pub arsenal: Arsenal,
+    /// How much time the owner of this board has left
+    pub clock: Clock,
 }
 
 impl Board {
     /// If this function errors, then the ship state was invalid
     pub const fn new(ships: ShipSet) -> Self {
+        let arsenal = ships.rules().arsenal;
+        let clock = Clock::new(ships.rules().clock);
         Self {
-            locals: [[Shot::Empty; 10]; 10],
+            locals: [EMPTY_ROW; 10],
             ships,
+            arsenal,
+            clock,
         }
     }
+    /// Returns `true` if `cell` is water that hasn't been shot at yet
+    pub fn can_fire_at(&self, cell: &Cell) -> bool {
+        self.shot(cell) == Shot::Empty && !self.ships.rules().terrain.is_land(*cell)
+    }
     pub fn fire(&mut self, cell: &Cell) -> Option<Shot> {
-        if *self.shot_mut(cell) != Shot::Empty {
+        if !self.can_fire_at(cell) {
             return None;
         }
         let outcome = self.ships.ref_for(*cell).map_or(Shot::Miss, Shot::Hit);
         self.update_cell(cell, outcome.clone());
+        if let Shot::Hit(ship) = &outcome {
+            // nothing can be next to a sunk ship, so save everyone the trouble,
+            // unless that would give away the sink
+            let rules = self.ships.rules();
+            if rules.placement == Placement::NoTouching
+                && rules.disclosure == Disclosure::Full
+                && self.sunk(ship)
+            {
+                for near in ship.surrounding(self.ships.rules().geometry) {
+                    if self.can_fire_at(&near) {
+                        self.update_cell(&near, Shot::Miss);
+                    }
+                }
+            }
+        }
         Some(outcome)
     }
+    /// Fires at every cell at once. If any of them can't be fired at,
+    /// or is picked twice, nothing is fired and this returns `None`.
+    pub fn fire_volley(&mut self, cells: &[Cell]) -> Option<Vec<Shot>> {
+        for (i, cell) in cells.iter().enumerate() {
+            if !self.can_fire_at(cell) || cells[..i].contains(cell) {
+                return None;
+            }
+        }
+        // a sinking shot may already have marked later targets as misses
+        cells
+            .iter()
+            .map(|cell| Some(self.fire(cell).unwrap_or_else(|| self.shot(cell))))
+            .collect()
+    }
+    /// Uses `weapon` against this board. Returns `None` if there was nothing
+    /// left to fire at, in which case the board is unchanged.
+    pub fn strike(
+        &mut self,
+        weapon: Weapon,
+        target: Cell,
+        heading: ShipRotation,
+    ) -> Option<Strike> {
+        let pattern = weapon.pattern(target, heading, self.ships.rules().geometry);
+        if weapon == Weapon::Radar {
+            let found = pattern
+                .iter()
+                .filter(|cell| self.ships.contains_ship(**cell))
+                .count();
+            return Some(Strike::Radar(found));
+        }
+        let mut fired = Vec::with_capacity(pattern.len());
+        for cell in pattern {
+            // torpedoes run aground, everything else just flies over land
+            if weapon == Weapon::Torpedo && self.ships.rules().terrain.is_land(cell) {
+                break;
+            }
+            if !self.can_fire_at(&cell) {
+                continue;
+            }
+            // a sinking shot may already have marked later cells as misses
+            let shot = self.fire(&cell).unwrap_or_else(|| self.shot(&cell));
+            let stops = weapon == Weapon::Torpedo && matches!(shot, Shot::Hit(_));
+            fired.push((cell, shot));
+            if stops {
+                break;
+            }
+        }
+        if fired.is_empty() {
+            return None;
+        }
+        Some(Strike::Fired(fired))
+    }
+    /// Moves the `index`th ship instead of firing. Only undamaged ships can
+    /// move, and never onto a cell that was already shot at, so every miss
+    /// still means there's no ship there. Returns `false` if the move isn't
+    /// allowed, in which case nothing changes.
+    pub fn move_ship(&mut self, index: usize, motion: Motion) -> bool {
+        let geometry = self.ships.rules().geometry;
+        let Some(ship) = self.ships.ships().nth(index).copied() else {
+            return false;
+        };
+        let Some(moved) = ship.moved(motion, geometry) else {
+            return false;
+        };
+        let untouched = |cells: Vec<Cell>| cells.iter().all(|cell| self.shot(cell) == Shot::Empty);
+        if !untouched(ship.occupies(geometry)) || !untouched(moved.occupies(geometry)) {
+            return false;
+        }
+        self.ships.relocate(index, moved)
+    }
+    /// How many cells can still be fired at
+    pub fn unfired(&self) -> usize {
+        self.targets().len()
+    }
+    /// Every cell that can still be fired at
+    pub fn targets(&self) -> Vec<Cell> {
+        (0..10)
+            .flat_map(|x| (0..10).map(move |y| Cell::new(x, y)))
+            .filter(|cell| self.can_fire_at(cell))
+            .collect()
+    }
+    pub fn sunk(&self, ship: &ShipState) -> bool {
+        ship.occupies(self.ships.rules().geometry)
+            .iter()
+            .all(|v| matches!(self.shot(v), Shot::Hit(_ship)))
+    }
+    /// How many ships of the fleet are still afloat
+    pub fn afloat(&self) -> usize {
+        self.ships.ships().filter(|ship| !self.sunk(ship)).count()
+    }
     pub fn lost(&self) -> bool {
         self.ships
             .occupied_cells()
             .iter()
             .all(|v| matches!(self.shot(v), Shot::Hit(_ship)))
     }
+    /// What the attacker gets told about `shot` on this board. Everything
+    /// is given away once the game is over.
+    pub fn report(&self, shot: &Shot) -> Report {
+        let ship = match shot {
+            Shot::Hit(ship) => ship,
+            Shot::Miss => return Report::Miss,
+            Shot::Empty => return Report::Nothing,
+        };
+        let sunk = self.sunk(ship);
+        match self.ships.rules().disclosure {
+            _ if sunk && self.lost() => Report::Sunk(ship.kind()),
+            Disclosure::Full if sunk => Report::Sunk(ship.kind()),
+            Disclosure::Full | Disclosure::ClassOnly => Report::Hit(Some(ship.kind())),
+            Disclosure::HitsOnly => Report::Hit(None),
+        }
+    }
     fn update_cell(&mut self, cell: &Cell, value: Shot) {
         let shot = self.shot_mut(cell);
         *shot = value;
@@ -45,17 +186,124 @@ impl Board {
     }
 }
 
+/// A board as it's saved. Which ship every hit belongs to is worked out again
+/// on load, and clocks start over.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedBoard {
+    ships: ShipSet,
+    /// Every cell that's been fired at, hit or not
+    fired: Vec<Cell>,
+    arsenal: Arsenal,
+}
+
+#[cfg(feature = "serde")]
+impl From<Board> for SavedBoard {
+    fn from(board: Board) -> Self {
+        let fired = (0..10)
+            .flat_map(|x| (0..10).map(move |y| Cell::new(x, y)))
+            .filter(|cell| board.shot(cell) != Shot::Empty)
+            .collect();
+        Self {
+            ships: board.ships,
+            fired,
+            arsenal: board.arsenal,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<SavedBoard> for Board {
+    fn from(saved: SavedBoard) -> Self {
+        let mut board = Self::new(saved.ships);
+        board.arsenal = saved.arsenal;
+        for cell in saved.fired {
+            let shot = board.ships.ref_for(cell).map_or(Shot::Miss, Shot::Hit);
+            board.update_cell(&cell, shot);
+        }
+        board
+    }
+}
+
 pub type RawBoard = [[Shot; 10]; 10];
 
+// Shot isn't Copy, but array repeats of consts are fine
+const EMPTY_ROW: [Shot; 10] = [Shot::EMPTY; 10];
+
+/// What came of using a weapon
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Strike {
+    /// How many ship cells the radar picked up
+    Radar(usize),
+    /// Every cell that was fired at, in order
+    Fired(Vec<(Cell, Shot)>),
+}
+
+/// A shot as the attacker sees it
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Report {
+    /// Hit something, and which kind of ship if the rules say
+    Hit(Option<ShipType>),
+    Sunk(ShipType),
+    Miss,
+    Nothing,
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "SavedShot", into = "SavedShot")
+)]
 pub enum Shot {
     Hit(Arc<ShipState>),
     Miss,
     Empty,
 }
 
+impl Shot {
+    const EMPTY: Self = Self::Empty;
+
+    pub const fn is_hit(&self) -> bool {
+        matches!(self, Self::Hit(_))
+    }
+}
+
 impl Default for Shot {
     fn default() -> Self {
         Self::Empty
     }
 }
+
+/// A shot on its own, with its own copy of the ship it hit
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+enum SavedShot {
+    Hit(ShipState),
+    Miss,
+    Empty,
+}
+
+#[cfg(feature = "serde")]
+impl From<Shot> for SavedShot {
+    fn from(shot: Shot) -> Self {
+        match shot {
+            Shot::Hit(ship) => Self::Hit(*ship),
+            Shot::Miss => Self::Miss,
+            Shot::Empty => Self::Empty,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<SavedShot> for Shot {
+    fn from(shot: SavedShot) -> Self {
+        match shot {
+            SavedShot::Hit(ship) => Self::Hit(Arc::new(ship)),
+            SavedShot::Miss => Self::Miss,
+            SavedShot::Empty => Self::Empty,
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use std::ops::{AddAssign, SubAssign};
+use std::str::FromStr;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "(usize, usize)", into = "(usize, usize)")
+)]
 pub struct Cell {
     x: usize,
     y: usize,
@@ -24,6 +30,73 @@ impl Cell {
     }
 }
 
+impl TryFrom<(usize, usize)> for Cell {
+    type Error = String;
+
+    fn try_from((x, y): (usize, usize)) -> Result<Self, Self::Error> {
+        if x >= 10 || y >= 10 {
+            return Err(format!("({x}, {y}) is off the board"));
+        }
+        Ok(Self::new(x, y))
+    }
+}
+
+impl From<Cell> for (usize, usize) {
+    fn from(cell: Cell) -> Self {
+        (cell.x, cell.y)
+    }
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // rows are lettered, columns numbered from 1, same as on screen
+        #[allow(clippy::cast_possible_truncation)]
+        let row = char::from(b'A' + self.y as u8);
+        write!(f, "{row}{}", self.x + 1)
+    }
+}
+
+/// Why some text isn't a cell like `B7`
+#[derive(Clone, PartialEq, Eq, Hash, Debug, thiserror::Error)]
+pub enum ParseCellError {
+    #[error("no cell given")]
+    Empty,
+    #[error("{0:?} isn't a cell, try something like B7")]
+    Malformed(String),
+    #[error("there's no row {0}, rows go from A to J")]
+    Row(char),
+    #[error("there's no column {0}, columns go from 1 to 10")]
+    Column(String),
+}
+
+impl FromStr for Cell {
+    type Err = ParseCellError;
+
+    /// Reads notation like `B7`, `b7` or `J10`: the row letter, then the column
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let mut chars = text.chars();
+        let row = chars.next().ok_or(ParseCellError::Empty)?;
+        let column = chars.as_str();
+        if !row.is_ascii_alphabetic()
+            || column.is_empty()
+            || !column.bytes().all(|digit| digit.is_ascii_digit())
+        {
+            return Err(ParseCellError::Malformed(text.to_string()));
+        }
+        let row = row.to_ascii_uppercase();
+        if !('A'..='J').contains(&row) {
+            return Err(ParseCellError::Row(row));
+        }
+        let x = column
+            .parse::<usize>()
+            .ok()
+            .filter(|column| (1..=10).contains(column))
+            .ok_or_else(|| ParseCellError::Column(column.to_string()))?;
+        Ok(Self::new(x - 1, row as usize - 'A' as usize))
+    }
+}
+
 impl AddAssign<(usize, usize)> for Cell {
     fn add_assign(&mut self, rhs: (usize, usize)) {
         // this is actually an implementation of modulo 10. weird, right?
@@ -50,3 +123,53 @@ impl SubAssign<(usize, usize)> for Cell {
         self.y -= rhs.1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_board_notation() {
+        assert_eq!("b7".parse(), Ok(Cell::new(6, 1)));
+        assert_eq!("B7".parse(), Ok(Cell::new(6, 1)));
+        assert_eq!("J10".parse(), Ok(Cell::new(9, 9)));
+        assert_eq!(" A1 ".parse(), Ok(Cell::new(0, 0)));
+    }
+
+    #[test]
+    fn rejects_cells_off_the_board() {
+        assert_eq!("K1".parse::<Cell>(), Err(ParseCellError::Row('K')));
+        assert_eq!(
+            "A11".parse::<Cell>(),
+            Err(ParseCellError::Column("11".to_string()))
+        );
+        assert_eq!(
+            "A0".parse::<Cell>(),
+            Err(ParseCellError::Column("0".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_cells() {
+        assert_eq!("".parse::<Cell>(), Err(ParseCellError::Empty));
+        assert_eq!(
+            "7B".parse::<Cell>(),
+            Err(ParseCellError::Malformed("7B".to_string()))
+        );
+        assert_eq!(
+            "B".parse::<Cell>(),
+            Err(ParseCellError::Malformed("B".to_string()))
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for x in 0..10 {
+            for y in 0..10 {
+                let cell = Cell::new(x, y);
+                assert_eq!(cell.to_string().parse(), Ok(cell));
+            }
+        }
+        assert_eq!(Cell::new(6, 1).to_string(), "B7");
+    }
+}
@@ -0,0 +1,144 @@
+//! Time controls. Local games check the clock between keypresses, and
+//! network rooms stop waiting on a player once theirs runs out.
+
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeControl {
+    /// Take as long as you like
+    #[default]
+    Unlimited,
+    /// Every turn has to be played within this long
+    PerTurn(Duration),
+    /// Chess style: each player has `total` for the whole game, and gets
+    /// `increment` back after every turn
+    Bank {
+        total: Duration,
+        increment: Duration,
+    },
+}
+
+impl TimeControl {
+    const PRESETS: [Self; 5] = [
+        Self::Unlimited,
+        Self::PerTurn(Duration::from_secs(30)),
+        Self::PerTurn(Duration::from_secs(10)),
+        Self::Bank {
+            total: Duration::from_secs(300),
+            increment: Duration::from_secs(5),
+        },
+        Self::Bank {
+            total: Duration::from_secs(120),
+            increment: Duration::from_secs(2),
+        },
+    ];
+
+    pub fn next(&mut self) {
+        let current = Self::PRESETS.iter().position(|v| v == self);
+        *self = Self::PRESETS[current.map_or(0, |i| (i + 1) % Self::PRESETS.len())];
+    }
+}
+
+impl std::fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unlimited => f.write_str("Unlimited"),
+            Self::PerTurn(limit) => write!(f, "{} per turn", Minutes(*limit)),
+            Self::Bank { total, increment } => {
+                write!(f, "{} + {} per turn", Minutes(*total), Minutes(*increment))
+            }
+        }
+    }
+}
+
+/// What happens to a player who runs out of time
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Timeout {
+    /// The game fires for them, at random
+    #[default]
+    RandomShot,
+    /// They lose
+    Forfeit,
+}
+
+impl Timeout {
+    pub fn next(&mut self) {
+        *self = match self {
+            Self::RandomShot => Self::Forfeit,
+            Self::Forfeit => Self::RandomShot,
+        };
+    }
+}
+
+impl std::fmt::Display for Timeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RandomShot => f.write_str("Random shot"),
+            Self::Forfeit => f.write_str("Forfeit"),
+        }
+    }
+}
+
+/// One player's clock
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    /// What's left in the bank, not counting the running turn
+    bank: Duration,
+    /// When the running turn started, if one is running
+    started: Option<Instant>,
+}
+
+impl Clock {
+    pub const fn new(control: TimeControl) -> Self {
+        let bank = match control {
+            TimeControl::Unlimited => Duration::ZERO,
+            TimeControl::PerTurn(limit) => limit,
+            TimeControl::Bank { total, .. } => total,
+        };
+        Self {
+            control,
+            bank,
+            started: None,
+        }
+    }
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+    /// Stops the clock at the end of a turn, adding the increment if there is one
+    pub fn stop(&mut self) {
+        match self.control {
+            TimeControl::Unlimited | TimeControl::PerTurn(_) => {}
+            TimeControl::Bank { increment, .. } => {
+                self.bank = self.remaining().unwrap_or_default() + increment;
+            }
+        }
+        self.started = None;
+    }
+    /// Time left right now, or `None` if there's no limit
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.control == TimeControl::Unlimited {
+            return None;
+        }
+        let elapsed = self
+            .started
+            .map_or(Duration::ZERO, |started| started.elapsed());
+        Some(self.bank.saturating_sub(elapsed))
+    }
+    /// Returns `true` if the player ran out of time
+    pub fn flagged(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+}
+
+/// Formats a duration like `4:05`
+pub struct Minutes(pub Duration);
+
+impl std::fmt::Display for Minutes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.0.as_secs();
+        write!(f, "{}:{:02}", secs / 60, secs % 60)
+    }
+}
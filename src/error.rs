@@ -1,9 +1,35 @@
+use crate::{cell::ParseCellError, ship::Problem};
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("Ship placement overflowed or overlapped!")]
-    InvalidShipState,
+    #[error("Invalid ship placement: {0}")]
+    InvalidShipState(Box<Problem>),
     #[error("I/O error")]
     IoFailed(#[from] tokio::io::Error),
     #[error("Failed to parse int: {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
+    #[error("Invalid cell: {0}")]
+    InvalidCell(#[from] ParseCellError),
+    #[error("Invalid map: {0}")]
+    InvalidMap(String),
+    #[error("Invalid layout at line {line}, column {column}: {message}")]
+    InvalidLayout {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("Invalid game log: {0}")]
+    InvalidLog(String),
+    #[cfg(feature = "serde")]
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "serde")]
+    #[error("Binary format error: {0}")]
+    Binary(#[from] postcard::Error),
+    #[error("{0}")]
+    Usage(String),
+    #[error("Saved by a newer version of the game (format {0})")]
+    UnsupportedVersion(u32),
+    #[error("Game {game} of the tournament crashed: {message}")]
+    GameCrashed { game: usize, message: String },
 }
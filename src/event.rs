@@ -0,0 +1,174 @@
+use crate::{
+    board::Strike,
+    cell::Cell,
+    game::Outcome,
+    ship::{Motion, ShipRotation, ShipState},
+    weapon::Weapon,
+};
+
+/// What a player does with their turn
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Action {
+    /// Shells at one or more cells at once
+    Volley(Vec<Cell>),
+    /// Any weapon but shells
+    Strike {
+        weapon: Weapon,
+        target: Cell,
+        heading: ShipRotation,
+    },
+    /// Sails one of their own ships instead of firing
+    Move { ship: usize, motion: Motion },
+}
+
+/// One thing that happened in a game. A game is nothing but the list of
+/// these, everything else can be rebuilt by playing them back.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event {
+    /// A player locked in their fleet, ships in fleet order
+    FleetCommitted {
+        player: usize,
+        ships: Vec<ShipState>,
+    },
+    /// A player took their turn. `defender` is whoever they were aiming at.
+    Fired {
+        player: usize,
+        defender: usize,
+        action: Action,
+    },
+    /// What came of the turn before it, kept so a replay can be checked
+    /// against what everyone saw at the time
+    Outcome {
+        player: usize,
+        hits: Vec<Cell>,
+        misses: Vec<Cell>,
+        /// Ship cells a radar sweep picked up
+        radar: Option<usize>,
+    },
+    Resigned {
+        player: usize,
+    },
+    /// A player's clock ran out. What that means is up to the rules.
+    TimedOut {
+        player: usize,
+    },
+}
+
+impl Event {
+    /// The record of `outcome`
+    pub fn outcome(player: usize, outcome: &Outcome) -> Self {
+        let mut hits = Vec::new();
+        let mut misses = Vec::new();
+        let mut radar = None;
+        let mut sort = |cell: Cell, hit: bool| {
+            if hit {
+                hits.push(cell);
+            } else {
+                misses.push(cell);
+            }
+        };
+        match outcome {
+            Outcome::Volley(shots) => {
+                for (cell, shot) in shots {
+                    sort(*cell, shot.is_hit());
+                }
+            }
+            Outcome::Strike(Strike::Fired(fired)) => {
+                for (cell, shot) in fired {
+                    sort(*cell, shot.is_hit());
+                }
+            }
+            Outcome::Strike(Strike::Radar(found)) => radar = Some(*found),
+            Outcome::Moved => {}
+        }
+        Self::Outcome {
+            player,
+            hits,
+            misses,
+            radar,
+        }
+    }
+}
+
+/// Cells the way they're written on screen, like `B7, C3`
+struct Cells<'a>(&'a [Cell]);
+
+impl std::fmt::Display for Cells<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, cell) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{cell}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Volley(cells) => write!(f, "shells at {}", Cells(cells)),
+            Self::Strike {
+                weapon: weapon @ Weapon::Torpedo,
+                target,
+                heading,
+            } => write!(f, "{weapon} from {target} heading {heading}"),
+            Self::Strike { weapon, target, .. } => write!(f, "{weapon} at {target}"),
+            Self::Move { ship, motion } => {
+                let (verb, way) = match motion {
+                    Motion::Forward => ("moves", "forward"),
+                    Motion::Backward => ("moves", "backward"),
+                    Motion::TurnLeft => ("turns", "left"),
+                    Motion::TurnRight => ("turns", "right"),
+                };
+                write!(f, "{verb} ship {} {way}", ship + 1)
+            }
+        }
+    }
+}
+
+// players are numbered from 1, like everywhere they're shown
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FleetCommitted { player, ships } => {
+                write!(f, "Player {} placed", player + 1)?;
+                for (i, ship) in ships.iter().enumerate() {
+                    let sep = if i == 0 { ' ' } else { ',' };
+                    write!(
+                        f,
+                        "{sep} {} at {} {}",
+                        ship.kind(),
+                        ship.pos(),
+                        ship.rotation()
+                    )?;
+                }
+                Ok(())
+            }
+            Self::Fired {
+                player,
+                defender,
+                action,
+            } => write!(
+                f,
+                "Player {} vs player {}: {action}",
+                player + 1,
+                defender + 1
+            ),
+            Self::Outcome {
+                radar: Some(found), ..
+            } => write!(f, "Radar picked up {found} ship cells"),
+            Self::Outcome { hits, misses, .. } => match (hits.is_empty(), misses.is_empty()) {
+                (true, true) => f.write_str("Nothing happened"),
+                (false, true) => write!(f, "Hit {}", Cells(hits)),
+                (true, false) => write!(f, "Missed {}", Cells(misses)),
+                (false, false) => write!(f, "Hit {}, missed {}", Cells(hits), Cells(misses)),
+            },
+            Self::Resigned { player } => write!(f, "Player {} resigned", player + 1),
+            Self::TimedOut { player } => write!(f, "Player {} ran out of time", player + 1),
+        }
+    }
+}
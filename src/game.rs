@@ -0,0 +1,416 @@
+use crate::{
+    board::{Board, Shot, Strike},
+    cell::Cell,
+    clock::{Clock, Timeout},
+    error::Error,
+    event::{Action, Event},
+    rules::{Mode, Rules},
+    ship::ShipSetBuilder,
+};
+
+/// What came of an action
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    Volley(Vec<(Cell, Shot)>),
+    Strike(Strike),
+    Moved,
+}
+
+/// Everyone's boards, whose turn it is, and how it got that way
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SavedGame", into = "SavedGame")
+)]
+pub struct Game {
+    rules: Rules,
+    boards: Vec<Board>,
+    /// Players who resigned or timed out, even if their fleet is still afloat
+    forfeited: Vec<bool>,
+    /// Who last played for each team
+    last_played: Vec<Option<usize>>,
+    current: usize,
+    log: Vec<Event>,
+}
+
+impl Game {
+    pub fn new(rules: &Rules, boards: Vec<Board>) -> Self {
+        let mode = rules.mode;
+        let mut last_played = vec![None; mode.teams()];
+        last_played[mode.team(0)] = Some(0);
+        let log = boards
+            .iter()
+            .enumerate()
+            .map(|(player, board)| Event::FleetCommitted {
+                player,
+                ships: board.ships.ships().copied().collect(),
+            })
+            .collect();
+        Self {
+            rules: rules.clone(),
+            forfeited: vec![false; boards.len()],
+            boards,
+            last_played,
+            current: 0,
+            log,
+        }
+    }
+    /// Rebuilds a game by playing `events` back under `rules`. Fails if the
+    /// events couldn't have happened, or didn't turn out the way they say.
+    pub fn replay(rules: &Rules, events: &[Event]) -> Result<Self, Error> {
+        let invalid = |why: &str| Error::InvalidLog(why.to_string());
+        // rules from a save file could say anything
+        if !(2..=Mode::MAX_PLAYERS).contains(&rules.mode.players()) {
+            return Err(invalid("the rules don't allow that many players"));
+        }
+        let mut fleets = vec![None; rules.mode.players()];
+        let mut rest = events;
+        // every fleet is locked in before the first shot
+        while let [Event::FleetCommitted { player, ships }, tail @ ..] = rest {
+            let mut builder = ShipSetBuilder::new(rules);
+            for (index, ship) in ships.iter().enumerate() {
+                if !builder.place(index, *ship) {
+                    return Err(invalid("fleet doesn't match the rules"));
+                }
+            }
+            let fleet = fleets
+                .get_mut(*player)
+                .ok_or_else(|| invalid("fleet committed for a player who isn't in the game"))?;
+            *fleet = Some(builder.build()?);
+            rest = tail;
+        }
+        let boards = fleets
+            .into_iter()
+            .map(|fleet| fleet.map(Board::new))
+            .collect::<Option<Vec<Board>>>()
+            .ok_or_else(|| invalid("not every fleet was committed"))?;
+        let mut game = Self::new(rules, boards);
+        for event in rest {
+            game.apply(event)?;
+        }
+        Ok(game)
+    }
+    fn apply(&mut self, event: &Event) -> Result<(), Error> {
+        let invalid = |why: &str| Err(Error::InvalidLog(format!("{why} ({event})")));
+        match event {
+            Event::FleetCommitted { .. } => return invalid("fleet committed mid-game"),
+            Event::Fired {
+                player,
+                defender,
+                action,
+            } => {
+                if *player != self.current {
+                    return invalid("a player fired out of turn");
+                }
+                if self.act(*defender, action.clone()).is_none() {
+                    return invalid("a player did something impossible");
+                }
+            }
+            // act has already recorded what really happened
+            Event::Outcome { .. } => {
+                if self.log.last() != Some(event) {
+                    return invalid("replay doesn't match what was recorded");
+                }
+            }
+            Event::Resigned { player } | Event::TimedOut { player }
+                if *player >= self.forfeited.len() =>
+            {
+                return invalid("a player who isn't in the game dropped out");
+            }
+            Event::Resigned { player } => self.resign(*player),
+            Event::TimedOut { player } => self.time_out(*player),
+        }
+        Ok(())
+    }
+    /// Everything that's happened so far, oldest first
+    pub fn log(&self) -> &[Event] {
+        &self.log
+    }
+    /// This game as it was before the last turn, or `None` if nobody has
+    /// played yet. Clocks start over.
+    pub fn rewound(&self) -> Option<Result<Self, Error>> {
+        let mut last = self.log.iter().rposition(|event| {
+            !matches!(event, Event::FleetCommitted { .. } | Event::Outcome { .. })
+        })?;
+        // a random shot goes with the timeout that caused it
+        if matches!(self.log[last], Event::Fired { .. })
+            && last > 0
+            && matches!(self.log[last - 1], Event::TimedOut { .. })
+        {
+            last -= 1;
+        }
+        Some(Self::replay(&self.rules, &self.log[..last]))
+    }
+    pub const fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    /// The player whose turn it is
+    pub const fn current(&self) -> usize {
+        self.current
+    }
+    pub fn board(&self, player: usize) -> &Board {
+        &self.boards[player]
+    }
+    pub fn clock_mut(&mut self, player: usize) -> &mut Clock {
+        &mut self.boards[player].clock
+    }
+    /// How many players the game started with
+    pub fn len(&self) -> usize {
+        self.boards.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.boards.is_empty()
+    }
+    /// Returns `true` if `player` has dropped out, sunk or not
+    pub fn is_out(&self, player: usize) -> bool {
+        self.forfeited[player] || self.boards[player].lost()
+    }
+    pub fn resign(&mut self, player: usize) {
+        self.log.push(Event::Resigned { player });
+        self.forfeit(player);
+    }
+    /// Records that `player` ran out of time. They lose if the rules say so,
+    /// otherwise whoever's playing for them should fire at random.
+    pub fn time_out(&mut self, player: usize) {
+        self.log.push(Event::TimedOut { player });
+        if self.rules.timeout == Timeout::Forfeit {
+            self.forfeit(player);
+        }
+    }
+    fn forfeit(&mut self, player: usize) {
+        self.forfeited[player] = true;
+        if player == self.current {
+            self.advance();
+        }
+    }
+    /// Plays the current player's turn against `defender`. Returns `None` if
+    /// that's not something they can do, in which case nothing changes.
+    pub fn act(&mut self, defender: usize, action: Action) -> Option<Outcome> {
+        let player = self.current;
+        let moving_ships = self.rules.moving_ships;
+        if !self.opponents().contains(&defender) {
+            return None;
+        }
+        let (attacker, target) = self.attack(defender);
+        let outcome = match &action {
+            Action::Volley(cells) => {
+                if cells.is_empty() {
+                    return None;
+                }
+                let shots = target.fire_volley(cells)?;
+                Outcome::Volley(cells.iter().copied().zip(shots).collect())
+            }
+            Action::Strike {
+                weapon,
+                target: cell,
+                heading,
+            } => {
+                if attacker
+                    .arsenal
+                    .charges(*weapon)
+                    .is_none_or(|left| left == 0)
+                {
+                    return None;
+                }
+                let strike = target.strike(*weapon, *cell, *heading)?;
+                attacker.arsenal.take(*weapon);
+                Outcome::Strike(strike)
+            }
+            Action::Move { ship, motion } => {
+                if !moving_ships || !attacker.move_ship(*ship, *motion) {
+                    return None;
+                }
+                Outcome::Moved
+            }
+        };
+        self.log.push(Event::Fired {
+            player,
+            defender,
+            action,
+        });
+        self.log.push(Event::outcome(player, &outcome));
+        self.advance();
+        Some(outcome)
+    }
+    /// Everyone on `team`, in turn order
+    pub fn team(&self, team: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).filter(move |player| self.rules.mode.team(*player) == team)
+    }
+    /// The current player's partner, if they have one still in the game
+    pub fn ally(&self) -> Option<usize> {
+        self.team(self.rules.mode.team(self.current))
+            .find(|player| *player != self.current && !self.is_out(*player))
+    }
+    /// Everyone the current player could fire at
+    pub fn opponents(&self) -> Vec<usize> {
+        let team = self.rules.mode.team(self.current);
+        (0..self.len())
+            .filter(|player| self.rules.mode.team(*player) != team && !self.is_out(*player))
+            .collect()
+    }
+    /// Passes the turn to the next team still in the game, and to whichever
+    /// of its players is next in line
+    fn advance(&mut self) {
+        let mode = self.rules.mode;
+        let team = mode.team(self.current);
+        for step in 1..=mode.teams() {
+            let next = (team + step) % mode.teams();
+            let members: Vec<usize> = self.team(next).collect();
+            let after = self.last_played[next]
+                .and_then(|last| members.iter().position(|player| *player == last))
+                .map_or(0, |i| i + 1);
+            let up = (0..members.len())
+                .map(|i| members[(after + i) % members.len()])
+                .find(|player| !self.is_out(*player));
+            if let Some(player) = up {
+                self.last_played[next] = Some(player);
+                self.current = player;
+                return;
+            }
+        }
+    }
+    /// Everyone on the last team standing, once there is one
+    pub fn winners(&self) -> Option<Vec<usize>> {
+        let mut left = (0..self.len())
+            .filter(|player| !self.is_out(*player))
+            .map(|player| self.rules.mode.team(player));
+        let team = left.next()?;
+        if left.any(|other| other != team) {
+            return None;
+        }
+        Some(self.team(team).collect())
+    }
+    /// The current player's board and `defender`'s, to play a turn with
+    fn attack(&mut self, defender: usize) -> (&mut Board, &mut Board) {
+        let attacker = self.current;
+        assert_ne!(attacker, defender, "players can't fire at themselves");
+        if attacker < defender {
+            let (low, high) = self.boards.split_at_mut(defender);
+            (&mut low[attacker], &mut high[0])
+        } else {
+            let (low, high) = self.boards.split_at_mut(attacker);
+            (&mut high[0], &mut low[defender])
+        }
+    }
+}
+
+/// A game is saved as its rules and log, and played back on load
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedGame {
+    rules: Rules,
+    log: Vec<Event>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Game> for SavedGame {
+    fn from(game: Game) -> Self {
+        Self {
+            rules: game.rules,
+            log: game.log,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SavedGame> for Game {
+    type Error = Error;
+
+    fn try_from(saved: SavedGame) -> Result<Self, Self::Error> {
+        Self::replay(&saved.rules, &saved.log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::BitBoard;
+
+    fn new_game(rules: &Rules) -> Game {
+        let boards = (0..rules.mode.players())
+            .map(|player| {
+                let mut fleet = ShipSetBuilder::new(rules);
+                assert!(fleet.randomize(player as u64));
+                Board::new(fleet.build().unwrap())
+            })
+            .collect();
+        Game::new(rules, boards)
+    }
+
+    /// Fires at the first open cells until `turns` turns have been played
+    fn play(game: &mut Game, turns: usize) {
+        for _ in 0..turns {
+            let defender = game.opponents()[0];
+            let cell = game.board(defender).targets()[0];
+            game.act(defender, Action::Volley(vec![cell])).unwrap();
+        }
+    }
+
+    #[test]
+    fn replay_rebuilds_the_game() {
+        let rules = Rules::default();
+        let mut game = new_game(&rules);
+        play(&mut game, 7);
+        game.resign(game.current());
+        let replayed = Game::replay(&rules, game.log()).unwrap();
+        assert_eq!(replayed.log(), game.log());
+        assert_eq!(replayed.current(), game.current());
+        assert_eq!(replayed.winners(), game.winners());
+        for player in 0..game.len() {
+            assert_eq!(
+                BitBoard::from(replayed.board(player)),
+                BitBoard::from(game.board(player))
+            );
+        }
+    }
+
+    #[test]
+    fn rewound_takes_back_one_turn() {
+        let rules = Rules::default();
+        let mut game = new_game(&rules);
+        assert!(game.rewound().is_none());
+        play(&mut game, 3);
+        let before = game.clone();
+        play(&mut game, 1);
+        let rewound = game.rewound().unwrap().unwrap();
+        assert_eq!(rewound.log(), before.log());
+        assert_eq!(rewound.current(), before.current());
+        for player in 0..game.len() {
+            assert_eq!(
+                BitBoard::from(rewound.board(player)),
+                BitBoard::from(before.board(player))
+            );
+        }
+    }
+
+    #[test]
+    fn replay_rejects_players_who_arent_there() {
+        let rules = Rules::default();
+        let game = new_game(&rules);
+        for event in [Event::Resigned { player: 2 }, Event::TimedOut { player: 9 }] {
+            let mut log = game.log().to_vec();
+            log.push(event);
+            assert!(matches!(
+                Game::replay(&rules, &log),
+                Err(Error::InvalidLog(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn replay_rejects_a_turn_out_of_order() {
+        let rules = Rules::default();
+        let mut game = new_game(&rules);
+        play(&mut game, 2);
+        let mut log = game.log().to_vec();
+        // player 2 fires twice running
+        let extra = log[log.len() - 2..].to_vec();
+        log.extend(extra);
+        assert!(matches!(
+            Game::replay(&rules, &log),
+            Err(Error::InvalidLog(_))
+        ));
+    }
+}
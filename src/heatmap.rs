@@ -0,0 +1,237 @@
+//! Where the ships an attacker hasn't found yet are most likely to be.
+//!
+//! Every way the remaining ships could lie that fits what the attacker has
+//! seen so far is counted once for each cell it covers. The more placements
+//! cover a cell, the better a shot it is.
+
+use crate::{
+    bitboard::Bits,
+    board::{Board, Report, Shot},
+    cell::Cell,
+    rules::{Placement, Rules},
+    ship::{ShipRotation, ShipState, ShipType},
+};
+
+/// Everything an attacker knows about a board
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Observation {
+    pub misses: Bits,
+    /// Hits on ships that haven't been reported sunk
+    pub hits: Bits,
+    /// Cells of every ship reported sunk
+    pub sunk: Bits,
+    /// Ships not reported sunk yet, in fleet order
+    pub remaining: Vec<ShipType>,
+}
+
+impl Observation {
+    /// Nothing fired yet
+    pub fn new(rules: &Rules) -> Self {
+        Self {
+            misses: Bits::EMPTY,
+            hits: Bits::EMPTY,
+            sunk: Bits::EMPTY,
+            remaining: rules.fleet.ships().to_vec(),
+        }
+    }
+    /// What the attacker has been told about `board`, going by the same
+    /// reports they got during the game
+    pub fn of(board: &Board) -> Self {
+        let geometry = board.ships.rules().geometry;
+        let mut out = Self::new(board.ships.rules());
+        let mut sunk: Vec<ShipState> = Vec::new();
+        for cell in Bits::ALL.cells() {
+            let shot = board.shot(&cell);
+            match (board.report(&shot), &shot) {
+                (Report::Sunk(_), Shot::Hit(ship)) if !sunk.contains(ship) => sunk.push(**ship),
+                (Report::Hit(_), _) => out.hits.insert(cell),
+                (Report::Miss, _) => out.misses.insert(cell),
+                _ => {}
+            }
+        }
+        for ship in sunk {
+            out.sink(Bits::ship(&ship, geometry), ship.kind());
+        }
+        out
+    }
+    /// Records `kind` as sunk, lying on `cells`
+    pub fn sink(&mut self, cells: Bits, kind: ShipType) {
+        self.sunk |= cells;
+        self.hits = self.hits & !cells;
+        if let Some(index) = self.remaining.iter().position(|left| *left == kind) {
+            self.remaining.remove(index);
+        }
+    }
+}
+
+/// One way a ship could lie
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct Spot {
+    cells: Bits,
+    /// Water around it, for no-touching rules
+    around: Bits,
+}
+
+/// Every spot each kind of ship could take on an empty board. Working
+/// these out is the slow part, so they're kept around between shots.
+#[derive(Clone, Debug)]
+pub struct Placements {
+    rules: Rules,
+    kinds: Vec<(ShipType, Vec<Spot>)>,
+}
+
+impl Placements {
+    pub fn new(rules: &Rules) -> Self {
+        let geometry = rules.geometry;
+        let land: Bits = rules.terrain.land_cells().into_iter().collect();
+        let mut kinds: Vec<(ShipType, Vec<Spot>)> = Vec::new();
+        for kind in rules.fleet.ships() {
+            if kinds.iter().any(|(known, _spots)| known == kind) {
+                continue;
+            }
+            let rotations = if rules.diagonals && kind.shape().is_line() {
+                &ShipRotation::ALL[..]
+            } else {
+                &ShipRotation::ALL[..4]
+            };
+            let mut spots = Vec::new();
+            for cell in Bits::ALL.cells() {
+                for rot in rotations {
+                    for mirrored in [false, true] {
+                        let ship = ShipState::new(cell, *rot, *kind).with_mirrored(mirrored);
+                        let cells = Bits::ship(&ship, geometry);
+                        if ship.overflows(geometry) || cells.intersects(land) {
+                            continue;
+                        }
+                        let around = ship.surrounding(geometry).into_iter().collect();
+                        spots.push(Spot { cells, around });
+                    }
+                }
+            }
+            // mirrored lines and symmetric shapes land on the same cells
+            spots.sort_unstable_by_key(|spot| spot.cells);
+            spots.dedup_by_key(|spot| spot.cells);
+            kinds.push((*kind, spots));
+        }
+        Self {
+            rules: rules.clone(),
+            kinds,
+        }
+    }
+    pub const fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    /// How likely each cell is to hold a ship, given `seen`
+    pub fn heatmap(&self, seen: &Observation) -> Heatmap {
+        let no_touching = self.rules.placement == Placement::NoTouching;
+        let blocked = seen.misses | seen.sunk;
+        let mut out = Heatmap {
+            counts: [0; 100],
+            targeted: [0; 100],
+        };
+        for (kind, spots) in &self.kinds {
+            let left = seen.remaining.iter().filter(|ship| *ship == kind).count();
+            if left == 0 {
+                continue;
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            let left = left as u32;
+            for spot in spots {
+                if spot.cells.intersects(blocked)
+                    || (no_touching && spot.around.intersects(seen.sunk))
+                {
+                    continue;
+                }
+                let on_hits = spot.cells.intersects(seen.hits);
+                for cell in (spot.cells & !seen.hits).cells() {
+                    let index = index(cell);
+                    out.counts[index] += left;
+                    if on_hits {
+                        out.targeted[index] += left;
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A count for every cell of the board, see [`Placements::heatmap`]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Heatmap {
+    counts: [u32; 100],
+    /// Only the placements running through a hit that hasn't sunk anything
+    targeted: [u32; 100],
+}
+
+const fn index(cell: Cell) -> usize {
+    cell.x() + 10 * cell.y()
+}
+
+impl Heatmap {
+    /// How many placements of the remaining ships cover `cell`. Cells already
+    /// fired at are always 0.
+    pub const fn get(&self, cell: Cell) -> u32 {
+        self.counts[index(cell)]
+    }
+    /// How many placements through an unsunk hit cover `cell`
+    pub const fn targeted(&self, cell: Cell) -> u32 {
+        self.targeted[index(cell)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(name: &str) -> Cell {
+        name.parse().unwrap()
+    }
+
+    /// Only the patrol boat left to find, on an otherwise empty board
+    fn patrol_boat(rules: &Rules) -> Observation {
+        Observation {
+            remaining: vec![ShipType::PATROL_BOAT],
+            ..Observation::new(rules)
+        }
+    }
+
+    #[test]
+    fn counts_every_placement() {
+        let rules = Rules::default();
+        let heatmap = Placements::new(&rules).heatmap(&patrol_boat(&rules));
+        assert_eq!(heatmap.get(cell("A1")), 2);
+        assert_eq!(heatmap.get(cell("J10")), 2);
+        assert_eq!(heatmap.get(cell("A5")), 3);
+        assert_eq!(heatmap.get(cell("E5")), 4);
+        assert_eq!(heatmap.targeted(cell("E5")), 0);
+    }
+
+    #[test]
+    fn misses_rule_out_placements() {
+        let rules = Rules::default();
+        let mut seen = patrol_boat(&rules);
+        seen.misses.insert(cell("E5"));
+        let heatmap = Placements::new(&rules).heatmap(&seen);
+        assert_eq!(heatmap.get(cell("E5")), 0);
+        for near in ["D5", "F5", "E4", "E6"] {
+            assert_eq!(heatmap.get(cell(near)), 3, "at {near}");
+        }
+        assert_eq!(heatmap.get(cell("E7")), 4);
+        assert_eq!(heatmap.get(cell("D4")), 4);
+    }
+
+    #[test]
+    fn hits_point_at_their_neighbours() {
+        let rules = Rules::default();
+        let mut seen = patrol_boat(&rules);
+        seen.hits.insert(cell("E5"));
+        let heatmap = Placements::new(&rules).heatmap(&seen);
+        assert_eq!(heatmap.get(cell("E5")), 0);
+        for near in ["D5", "F5", "E4", "E6"] {
+            assert_eq!(heatmap.targeted(cell(near)), 1, "at {near}");
+            assert_eq!(heatmap.get(cell(near)), 4, "at {near}");
+        }
+        assert_eq!(heatmap.targeted(cell("D4")), 0);
+    }
+}
@@ -0,0 +1,472 @@
+//! Fleet layouts as plain text, to keep favourite setups around.
+//!
+//! The grid form is ten rows of ten characters: `.` for water, `#` for land
+//! and the first letter of a ship's name wherever that ship is.
+//!
+//! ```text
+//! AAAAA.....
+//! ..........
+//! B.........
+//! B...DDD...
+//! ```
+//!
+//! The list form has one ship per line: its name, the cell its bow is on,
+//! which way the rest of it trails, and `mirrored` if it's flipped.
+//!
+//! ```text
+//! Aircraft Carrier A1 right
+//! Battleship C1 down
+//! ```
+//!
+//! Grids can't tell apart two ships of the same kind that touch, so
+//! [`to_text`] only writes one when it reads back exactly.
+
+use crate::{
+    bitboard::Bits,
+    cell::{Cell, ParseCellError},
+    error::Error,
+    rules::Rules,
+    ship::{ShipRotation, ShipSet, ShipSetBuilder, ShipState, ShipType},
+};
+
+/// Reads a layout in either form, and checks it against `rules`
+pub fn parse(text: &str, rules: &Rules) -> Result<ShipSet, Error> {
+    let first = text.lines().find(|line| !line.trim().is_empty());
+    if first.is_some_and(|line| is_grid_row(line, rules)) {
+        parse_grid(text, rules)
+    } else {
+        parse_list(text, rules)
+    }
+}
+
+/// Grid rows are one word of water, land and ship letters. A lone ship
+/// name is a list entry with the rest missing, even if it's 10 letters long.
+fn is_grid_row(line: &str, rules: &Rules) -> bool {
+    let line = line.trim();
+    line.chars()
+        .all(|ch| matches!(ch, '.' | '#') || ch.is_ascii_alphabetic())
+        && !rules
+            .fleet
+            .ships()
+            .iter()
+            .any(|kind| names_match(*kind, line))
+}
+
+/// The grid if it says exactly where every ship is, otherwise the list
+pub fn to_text(ships: &ShipSet) -> String {
+    let grid = to_grid(ships);
+    let geometry = ships.rules().geometry;
+    let exact = parse(&grid, ships.rules()).is_ok_and(|read| {
+        read.ships()
+            .zip(ships.ships())
+            .all(|(read, ship)| Bits::ship(read, geometry) == Bits::ship(ship, geometry))
+    });
+    if exact {
+        grid
+    } else {
+        to_list(ships)
+    }
+}
+
+/// The first letter of the ship's name, which is how grids show it
+pub fn letter(kind: ShipType) -> char {
+    kind.name()
+        .chars()
+        .next()
+        .map_or('?', |letter| letter.to_ascii_uppercase())
+}
+
+pub fn to_grid(ships: &ShipSet) -> String {
+    let mut out = String::with_capacity(110);
+    for y in 0..10 {
+        for x in 0..10 {
+            let cell = Cell::new(x, y);
+            out.push(match ships.ship_in(cell) {
+                Some(ship) => letter(ship.kind()),
+                None if ships.rules().terrain.is_land(cell) => '#',
+                None => '.',
+            });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub fn to_list(ships: &ShipSet) -> String {
+    let mut out = String::new();
+    for ship in ships.ships() {
+        out.push_str(&format!(
+            "{} {} {}",
+            ship.kind(),
+            ship.pos(),
+            ship.rotation()
+        ));
+        // flipping a straight ship changes nothing
+        if ship.is_mirrored() && !ship.kind().shape().is_line() {
+            out.push_str(" mirrored");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn invalid(line: usize, column: usize, message: impl Into<String>) -> Error {
+    Error::InvalidLayout {
+        line,
+        column,
+        message: message.into(),
+    }
+}
+
+fn parse_rotation(text: &str) -> Option<ShipRotation> {
+    ShipRotation::ALL
+        .into_iter()
+        .find(|rot| rot.to_string().eq_ignore_ascii_case(text))
+}
+
+fn parse_list(text: &str, rules: &Rules) -> Result<ShipSet, Error> {
+    let fleet = &rules.fleet;
+    let mut builder = ShipSetBuilder::new(rules);
+    // which line each ship came from, to point at when something's wrong
+    let mut lines = vec![0; fleet.len()];
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        if line.trim().is_empty() || line.trim_start().starts_with("//") {
+            continue;
+        }
+        let mut words: Vec<(usize, &str)> = line
+            .split(' ')
+            .scan(1, |column, word| {
+                let start = *column;
+                *column += word.len() + 1;
+                Some((start, word))
+            })
+            .filter(|(_column, word)| !word.is_empty())
+            .collect();
+        let mirrored = words
+            .last()
+            .is_some_and(|(_column, word)| word.eq_ignore_ascii_case("mirrored"));
+        if mirrored {
+            words.pop();
+        }
+        let [name @ .., (cell_at, cell), (rot_at, rot)] = &words[..] else {
+            return Err(invalid(
+                number,
+                1,
+                "expected a ship, a cell and a direction",
+            ));
+        };
+        let Some(rot) = parse_rotation(rot) else {
+            return Err(invalid(number, *rot_at, format!("{rot} isn't a direction")));
+        };
+        let pos: Cell = cell
+            .parse()
+            .map_err(|e: ParseCellError| invalid(number, *cell_at, e.to_string()))?;
+        let name: Vec<&str> = name.iter().map(|(_column, word)| *word).collect();
+        let name = name.join(" ");
+        let index = (0..fleet.len())
+            .filter(|index| builder.placed(*index).is_none())
+            .find(|index| names_match(fleet.ships()[*index], &name))
+            .ok_or_else(|| invalid(number, 1, format!("no {name} left to place")))?;
+        let kind = fleet.ships()[index];
+        builder.place(
+            index,
+            ShipState::new(pos, rot, kind).with_mirrored(mirrored),
+        );
+        lines[index] = number;
+    }
+    let geometry = rules.geometry;
+    let end = text.lines().count() + 1;
+    finish(&builder, end, |cell| {
+        // where ships clash, blame the one written down later
+        let line = (0..fleet.len())
+            .filter(|index| {
+                builder
+                    .placed(*index)
+                    .is_some_and(|ship| ship.occupies(geometry).contains(&cell))
+            })
+            .map(|index| lines[index])
+            .max()
+            .unwrap_or(end);
+        (line, 1)
+    })
+}
+
+/// Ship names match ignoring case, or on any one word, so `carrier` finds the
+/// Aircraft Carrier
+fn names_match(kind: ShipType, name: &str) -> bool {
+    kind.name().eq_ignore_ascii_case(name)
+        || kind
+            .name()
+            .split(' ')
+            .any(|word| word.eq_ignore_ascii_case(name))
+}
+
+fn parse_grid(text: &str, rules: &Rules) -> Result<ShipSet, Error> {
+    let rows: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.trim_end()))
+        .filter(|(_number, line)| !line.is_empty())
+        .collect();
+    if rows.len() != 10 {
+        let line = rows
+            .get(10)
+            .map_or(text.lines().count(), |(number, _row)| *number);
+        return Err(invalid(
+            line,
+            1,
+            format!("expected 10 rows, found {}", rows.len()),
+        ));
+    }
+    // cells covered by each letter, in reading order
+    let mut letters: Vec<(char, Vec<Cell>)> = Vec::new();
+    for (y, (number, row)) in rows.iter().enumerate() {
+        if row.chars().count() != 10 {
+            return Err(invalid(*number, 1, "rows must be 10 cells wide"));
+        }
+        for (x, ch) in row.chars().enumerate() {
+            let ch = ch.to_ascii_uppercase();
+            match ch {
+                '.' | '#' => {}
+                'A'..='Z' => match letters.iter_mut().find(|(letter, _cells)| *letter == ch) {
+                    Some((_letter, cells)) => cells.push(Cell::new(x, y)),
+                    None => letters.push((ch, vec![Cell::new(x, y)])),
+                },
+                _ => {
+                    return Err(invalid(
+                        *number,
+                        x + 1,
+                        format!("{ch} isn't a ship or water"),
+                    ))
+                }
+            }
+        }
+    }
+    let mut builder = ShipSetBuilder::new(rules);
+    for (ch, cells) in letters {
+        let kinds: Vec<usize> = (0..rules.fleet.len())
+            .filter(|index| letter(rules.fleet.ships()[*index]) == ch)
+            .collect();
+        let cell = cells[0];
+        if kinds.is_empty() {
+            return Err(invalid(
+                rows[cell.y()].0,
+                cell.x() + 1,
+                format!("there's no ship starting with {ch} in this fleet"),
+            ));
+        }
+        let Some(ships) = cover(&builder, &cells, &kinds) else {
+            return Err(invalid(
+                rows[cell.y()].0,
+                cell.x() + 1,
+                format!("can't make out the ships marked {ch}"),
+            ));
+        };
+        for (index, ship) in ships {
+            builder.place(index, ship);
+        }
+    }
+    let end = text.lines().count() + 1;
+    finish(&builder, end, |cell| (rows[cell.y()].0, cell.x() + 1))
+}
+
+/// Works out which of the fleet's `kinds` cover exactly `cells`, and where
+fn cover(
+    builder: &ShipSetBuilder,
+    cells: &[Cell],
+    kinds: &[usize],
+) -> Option<Vec<(usize, ShipState)>> {
+    let Some(first) = cells.first() else {
+        return Some(Vec::new());
+    };
+    let geometry = builder.rules().geometry;
+    for (i, index) in kinds.iter().enumerate() {
+        let kind = builder.fleet().ships()[*index];
+        // ships of the same kind are interchangeable, so only try the first
+        if kinds[..i]
+            .iter()
+            .any(|other| builder.fleet().ships()[*other] == kind)
+        {
+            continue;
+        }
+        let rotations = if builder.rules().diagonals && kind.shape().is_line() {
+            &ShipRotation::ALL[..]
+        } else {
+            &ShipRotation::ALL[..4]
+        };
+        let placements = (0..100).flat_map(|pos| {
+            rotations.iter().flat_map(move |rot| {
+                [false, true].map(|mirrored| {
+                    ShipState::new(Cell::new(pos % 10, pos / 10), *rot, kind)
+                        .with_mirrored(mirrored)
+                })
+            })
+        });
+        for ship in placements {
+            let occupies = ship.occupies(geometry);
+            if ship.overflows(geometry)
+                || !occupies.contains(first)
+                || !occupies.iter().all(|cell| cells.contains(cell))
+            {
+                continue;
+            }
+            let rest: Vec<Cell> = cells
+                .iter()
+                .copied()
+                .filter(|cell| !occupies.contains(cell))
+                .collect();
+            let others: Vec<usize> = kinds
+                .iter()
+                .copied()
+                .filter(|other| other != index)
+                .collect();
+            if let Some(mut found) = cover(builder, &rest, &others) {
+                found.push((*index, ship));
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+/// Builds the layout, blaming the first problem on where `locate` says its
+/// first cell was written down. Missing ships are blamed on the `end` line.
+fn finish(
+    builder: &ShipSetBuilder,
+    end: usize,
+    locate: impl Fn(Cell) -> (usize, usize),
+) -> Result<ShipSet, Error> {
+    if let Some(problem) = builder.problems().into_iter().next() {
+        let (line, column) = problem
+            .cells()
+            .first()
+            .map_or((end, 1), |cell| locate(*cell));
+        return Err(invalid(line, column, problem.to_string()));
+    }
+    builder.build().map_err(|e| match e {
+        Error::InvalidShipState(problem) => invalid(end, 1, problem.to_string()),
+        e => e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ship::Fleet;
+
+    const LIST: &str = "\
+Aircraft Carrier A1 right
+Battleship C1 down
+Destroyer C3 right
+Submarine J8 right
+Patrol Boat E10 up
+";
+
+    fn cells(ships: &ShipSet) -> Vec<Bits> {
+        let geometry = ships.rules().geometry;
+        ships
+            .ships()
+            .map(|ship| Bits::ship(ship, geometry))
+            .collect()
+    }
+
+    fn error_at(result: Result<ShipSet, Error>) -> (usize, usize, String) {
+        match result {
+            Err(Error::InvalidLayout {
+                line,
+                column,
+                message,
+            }) => (line, column, message),
+            other => panic!("expected a layout error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn list_round_trips() {
+        let rules = Rules::default();
+        let ships = parse(LIST, &rules).unwrap();
+        assert_eq!(to_list(&ships), LIST);
+        assert_eq!(
+            cells(&parse(&to_list(&ships), &rules).unwrap()),
+            cells(&ships)
+        );
+    }
+
+    #[test]
+    fn grid_round_trips() {
+        let rules = Rules::default();
+        let ships = parse(LIST, &rules).unwrap();
+        let grid = to_grid(&ships);
+        assert!(grid.starts_with("AAAAA.....\n"));
+        assert_eq!(cells(&parse(&grid, &rules).unwrap()), cells(&ships));
+        assert_eq!(to_text(&ships), grid);
+    }
+
+    #[test]
+    fn ambiguous_grids_export_as_a_list() {
+        let rules = Rules {
+            fleet: Fleet::new(vec![ShipType::CRUISER; 3]),
+            ..Rules::default()
+        };
+        // three cruisers side by side make a square a grid reads either way
+        let mut lists = 0;
+        for text in [
+            "Cruiser A1 right\nCruiser B1 right\nCruiser C1 right\n",
+            "Cruiser A1 down\nCruiser A2 down\nCruiser A3 down\n",
+        ] {
+            let ships = parse(text, &rules).unwrap();
+            let exported = to_text(&ships);
+            assert_eq!(cells(&parse(&exported, &rules).unwrap()), cells(&ships));
+            if exported == to_list(&ships) {
+                lists += 1;
+            }
+        }
+        assert_eq!(lists, 1);
+    }
+
+    #[test]
+    fn unknown_ship_name() {
+        let rules = Rules::default();
+        let text = LIST.replace("Destroyer C3", "Frigate C3");
+        let (line, column, message) = error_at(parse(&text, &rules));
+        assert_eq!((line, column), (3, 1));
+        assert!(message.contains("Frigate"), "{message}");
+    }
+
+    #[test]
+    fn bad_cell_points_at_the_cell() {
+        let rules = Rules::default();
+        let text = LIST.replace("J8", "K8");
+        let (line, column, _message) = error_at(parse(&text, &rules));
+        assert_eq!((line, column), (4, 11));
+    }
+
+    #[test]
+    fn overlap_blames_the_later_ship() {
+        let rules = Rules::default();
+        let text = LIST.replace("Destroyer C3 right", "Destroyer C1 down");
+        let (line, column, _message) = error_at(parse(&text, &rules));
+        assert_eq!((line, column), (3, 1));
+    }
+
+    #[test]
+    fn lone_ship_name_is_a_list_line() {
+        let rules = Rules::default();
+        for name in ["Battleship", "Submarine"] {
+            let (line, column, message) = error_at(parse(name, &rules));
+            assert_eq!((line, column), (1, 1));
+            assert!(message.contains("expected a ship"), "{message}");
+        }
+    }
+
+    #[test]
+    fn unknown_grid_letter() {
+        let rules = Rules::default();
+        let ships = parse(LIST, &rules).unwrap();
+        let grid = to_grid(&ships).replacen("AAAAA", "ZZZZZ", 1);
+        let (line, column, message) = error_at(parse(&grid, &rules));
+        assert_eq!((line, column), (1, 1));
+        assert!(message.contains('Z'), "{message}");
+    }
+}
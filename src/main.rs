@@ -1,12 +1,27 @@
 #![allow(clippy::module_name_repetitions)]
+mod ai;
+mod bench;
+mod bitboard;
 mod board;
 mod cell;
+mod clock;
 mod error;
+mod event;
+mod game;
+mod heatmap;
+mod layout;
 mod req_resp;
+mod room;
+mod rules;
+#[cfg(feature = "serde")]
+mod save;
 mod ship;
 mod stream;
+mod terrain;
+mod tournament;
 mod ui;
 mod util;
+mod weapon;
 
 use std::collections::HashMap;
 use std::io::Stdout;
@@ -20,11 +35,49 @@ use tokio::task::JoinSet;
 
 use cell::Cell;
 
-use crate::req_resp::ReqRespClient;
 pub use error::Error;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("bench") => {
+            let games = args.next().map_or(Ok(10_000), |games| games.parse())?;
+            bench::run(games);
+            return Ok(());
+        }
+        Some("play") => {
+            // the UI blocks on the keyboard, so keep it off the runtime
+            tokio::task::spawn_blocking(ui::play).await??;
+            return Ok(());
+        }
+        Some("tournament") => {
+            let parsed = tournament::Tournament::from_args(args)
+                .and_then(|(tournament, format)| tournament.check().map(|()| (tournament, format)));
+            let (tournament, format) = match parsed {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(2);
+                }
+            };
+            let stats = match tournament.run() {
+                Ok(stats) => stats,
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            };
+            let out = match format {
+                tournament::Format::Text => tournament::to_text(&stats, tournament.seed),
+                tournament::Format::Csv => tournament::to_csv(&stats, tournament.seed),
+                tournament::Format::Json => tournament::to_json(&stats, tournament.seed),
+            };
+            print!("{out}");
+            return Ok(());
+        }
+        _ => {}
+    }
     let addr = SocketAddr::from(([0, 0, 0, 0], 1967));
     let listener = TcpListener::bind(addr).await?;
     let mut tasks: JoinSet<()> = JoinSet::new();
@@ -40,14 +93,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 continue;
             }
         };
-        tasks.spawn(stream::handle_stream(stream));
+        tasks.spawn(stream::handle_stream(stream, state.pending_rooms.clone()));
     }
     while tasks.join_next().await.is_some() {}
     Ok(())
 }
 
 pub struct State {
-    pending_rooms: Arc<Mutex<HashMap<String, ReqRespClient<String, String>>>>,
+    pending_rooms: room::Rooms,
 }
 
 impl State {
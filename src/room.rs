@@ -0,0 +1,528 @@
+//! A game played over the network. Each room runs on its own task, owns the
+//! game, and hears from the players' connections over a channel.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use owo_colors::OwoColorize;
+use rand::seq::SliceRandom;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    board::{Board, Report, Shot},
+    cell::Cell,
+    clock::{Minutes, Timeout},
+    event::Action,
+    game::{Game, Outcome},
+    rules::Rules,
+};
+
+/// Rooms still waiting on players, by name
+pub type Rooms = Arc<Mutex<HashMap<String, mpsc::UnboundedSender<ToRoom>>>>;
+
+/// What a connection tells the room it's in
+pub enum ToRoom {
+    /// Asks for a seat, answered with the player number, or `None` if the
+    /// game already started
+    Join {
+        out: mpsc::UnboundedSender<String>,
+        seat: oneshot::Sender<Option<usize>>,
+    },
+    /// Something a player typed
+    Line(usize, String),
+    /// A player hung up
+    Left(usize),
+}
+
+/// Why a room stopped before the game was over
+enum Closed {
+    /// Everyone's gone
+    Empty,
+    /// A player left before the game started
+    Left(usize),
+}
+
+struct Room {
+    name: String,
+    rules: Rules,
+    rooms: Rooms,
+    inbox: mpsc::UnboundedReceiver<ToRoom>,
+    /// Where to send each player's screen, until they hang up
+    outs: Vec<Option<mpsc::UnboundedSender<String>>>,
+    /// Whether the room can still be found by name
+    listed: bool,
+}
+
+pub async fn run(name: String, rules: Rules, rooms: Rooms, inbox: mpsc::UnboundedReceiver<ToRoom>) {
+    let mut room = Room {
+        name,
+        rules,
+        rooms,
+        inbox,
+        outs: Vec::new(),
+        listed: true,
+    };
+    let played = match room.fill().await {
+        Ok(()) => match room.place().await {
+            Ok(boards) => room.play(boards).await,
+            Err(closed) => Err(closed),
+        },
+        Err(closed) => Err(closed),
+    };
+    room.unlist();
+    if let Err(Closed::Left(player)) = played {
+        room.tell_all(&format!(
+            "Player {} left before the game started, so it's off.\n",
+            player + 1
+        ));
+    }
+}
+
+impl Room {
+    /// Seats players until there are enough to start
+    async fn fill(&mut self) -> Result<(), Closed> {
+        let players = self.rules.mode.players();
+        while self.outs.len() < players {
+            match self.inbox.recv().await.ok_or(Closed::Empty)? {
+                ToRoom::Join { out, seat } => {
+                    if seat.send(Some(self.outs.len())).is_err() {
+                        continue;
+                    }
+                    self.outs.push(Some(out));
+                    self.tell_all(&format!(
+                        "Player {} joined room {} ({} of {players}).\n",
+                        self.outs.len(),
+                        self.name,
+                        self.outs.len()
+                    ));
+                }
+                ToRoom::Line(player, _line) => {
+                    let waiting = players - self.outs.len();
+                    self.tell(player, &format!("Waiting on {waiting} more players...\n"));
+                }
+                ToRoom::Left(player) => return Err(Closed::Left(player)),
+            }
+        }
+        // full rooms can't be joined, so a new one can take the name
+        self.unlist();
+        Ok(())
+    }
+    /// Deals everyone a random fleet, and rerolls it until they're happy
+    async fn place(&mut self) -> Result<Vec<Board>, Closed> {
+        let mut fleets = Vec::with_capacity(self.outs.len());
+        for _ in 0..self.outs.len() {
+            fleets.push(
+                crate::ai::random_fleet(&self.rules, &mut rand::thread_rng())
+                    .ok_or(Closed::Empty)?,
+            );
+        }
+        let mut ready = vec![false; fleets.len()];
+        for (player, ships) in fleets.iter().enumerate() {
+            self.tell(player, &placing(&Board::new(ships.clone())));
+        }
+        while ready.contains(&false) {
+            match self.inbox.recv().await.ok_or(Closed::Empty)? {
+                ToRoom::Join { seat, .. } => {
+                    let _ = seat.send(None);
+                }
+                ToRoom::Line(player, _line) if ready[player] => {
+                    self.tell(player, "Waiting on the others to place their ships...\n");
+                }
+                ToRoom::Line(player, line) if line.is_empty() => {
+                    ready[player] = true;
+                    self.tell(player, "Waiting on the others to place their ships...\n");
+                }
+                ToRoom::Line(player, _line) => {
+                    fleets[player] = crate::ai::random_fleet(&self.rules, &mut rand::thread_rng())
+                        .ok_or(Closed::Empty)?;
+                    self.tell(player, &placing(&Board::new(fleets[player].clone())));
+                }
+                ToRoom::Left(player) => return Err(Closed::Left(player)),
+            }
+        }
+        Ok(fleets.into_iter().map(Board::new).collect())
+    }
+    async fn play(&mut self, boards: Vec<Board>) -> Result<(), Closed> {
+        let mut game = Game::new(&self.rules, boards);
+        let mut news = String::new();
+        let winners = loop {
+            if let Some(winners) = game.winners() {
+                break winners;
+            }
+            let player = game.current();
+            self.show(&game, &news);
+            news = self.turn(&mut game, player).await?;
+        };
+        self.show(&game, &news);
+        self.tell_all(&match &winners[..] {
+            [winner] => format!("Player {} wins!\n", winner + 1),
+            _ => {
+                let names: Vec<String> = winners.iter().map(|p| (p + 1).to_string()).collect();
+                format!("Players {} win!\n", names.join(" and "))
+            }
+        });
+        Ok(())
+    }
+    /// Waits on `player` to fire, for as long as their clock lets them.
+    /// Returns what everyone should be told happened.
+    async fn turn(&mut self, game: &mut Game, player: usize) -> Result<String, Closed> {
+        game.clock_mut(player).start();
+        loop {
+            let msg = match game.board(player).clock.remaining() {
+                None => self.inbox.recv().await,
+                Some(left) => match tokio::time::timeout(left, self.inbox.recv()).await {
+                    Ok(msg) => msg,
+                    Err(_elapsed) => return Ok(self.time_out(game, player)),
+                },
+            };
+            match msg.ok_or(Closed::Empty)? {
+                ToRoom::Join { seat, .. } => {
+                    let _ = seat.send(None);
+                }
+                ToRoom::Line(from, line)
+                    if line.eq_ignore_ascii_case("resign") && !game.is_out(from) =>
+                {
+                    game.resign(from);
+                    let news = format!("Player {} resigned.", from + 1);
+                    if from == player || game.winners().is_some() {
+                        game.clock_mut(player).stop();
+                        return Ok(news);
+                    }
+                    self.tell_all(&format!("{news}\n"));
+                }
+                ToRoom::Line(from, _line) if from != player => {
+                    self.tell(from, &format!("It's player {}'s turn.\n", player + 1));
+                }
+                ToRoom::Line(_from, line) => match order(&line, &game.opponents())
+                    .and_then(|(defender, rest)| Ok((defender, aim(rest, shots(game, defender))?)))
+                {
+                    Ok((defender, cells)) => {
+                        let Some(Outcome::Volley(outcome)) =
+                            game.act(defender, Action::Volley(cells))
+                        else {
+                            self.tell(
+                                player,
+                                "You can't fire there. Pick cells you haven't fired at.\n> ",
+                            );
+                            continue;
+                        };
+                        game.clock_mut(player).stop();
+                        return Ok(fired(game.board(defender), player, defender, &outcome));
+                    }
+                    Err(e) => self.tell(player, &format!("{e}\n> ")),
+                },
+                ToRoom::Left(from) => {
+                    self.outs[from] = None;
+                    if self.outs.iter().all(Option::is_none) {
+                        return Err(Closed::Empty);
+                    }
+                    if game.is_out(from) {
+                        continue;
+                    }
+                    game.resign(from);
+                    let news = format!("Player {} left the game.", from + 1);
+                    if from == player || game.winners().is_some() {
+                        game.clock_mut(player).stop();
+                        return Ok(news);
+                    }
+                    self.tell_all(&format!("{news}\n"));
+                }
+            }
+        }
+    }
+    /// Plays out `player` running out of time, however the rules say to
+    fn time_out(&self, game: &mut Game, player: usize) -> String {
+        game.clock_mut(player).stop();
+        game.time_out(player);
+        match self.rules.timeout {
+            Timeout::RandomShot => {
+                let mut rng = rand::thread_rng();
+                let defender = *game.opponents().choose(&mut rng).unwrap_or(&player);
+                let mut cells = game.board(defender).targets();
+                cells.shuffle(&mut rng);
+                cells.truncate(shots(game, defender));
+                match game.act(defender, Action::Volley(cells)) {
+                    Some(Outcome::Volley(outcome)) => format!(
+                        "Out of time! {}",
+                        fired(game.board(defender), player, defender, &outcome)
+                    ),
+                    _ => format!("Player {} ran out of time.", player + 1),
+                }
+            }
+            Timeout::Forfeit => {
+                format!("Player {} ran out of time, and forfeits!", player + 1)
+            }
+        }
+    }
+    /// Sends everyone their own view of the game
+    fn show(&self, game: &Game, news: &str) {
+        for player in 0..self.outs.len() {
+            let mut screen = view(game, player);
+            screen.push_str(news);
+            screen.push('\n');
+            if game.winners().is_some() {
+                // the winners get announced right after
+            } else if game.is_out(player) {
+                screen.push_str("You're out, but you can keep watching.\n");
+            } else if game.current() == player {
+                let shots = self.rules.salvo.shots(game.board(player));
+                let example = match game.opponents()[..] {
+                    [_only] => "like B7".to_string(),
+                    [first, ..] => format!("and who at, like {} B7", first + 1),
+                    [] => String::new(),
+                };
+                let clock = game
+                    .board(player)
+                    .clock
+                    .remaining()
+                    .map(|left| format!(", you have {}", Minutes(left)))
+                    .unwrap_or_default();
+                screen.push_str(&format!(
+                    "Your turn{clock}. Fire {shots} {}, {example}, or type resign.\n> ",
+                    if shots == 1 { "shot" } else { "shots" }
+                ));
+            } else {
+                screen.push_str(&format!("Waiting on player {}...\n", game.current() + 1));
+            }
+            self.tell(player, &screen);
+        }
+    }
+    fn tell(&self, player: usize, text: &str) {
+        if let Some(out) = &self.outs[player] {
+            let _ = out.send(text.to_string());
+        }
+    }
+    fn tell_all(&self, text: &str) {
+        for player in 0..self.outs.len() {
+            self.tell(player, text);
+        }
+    }
+    /// Takes the room off the list of ones that can be joined, so a new one
+    /// can have its name
+    fn unlist(&mut self) {
+        if std::mem::take(&mut self.listed) {
+            let mut rooms = self
+                .rooms
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            rooms.remove(&self.name);
+        }
+    }
+}
+
+/// Every board titled and drawn for `player`: their own fleet and their
+/// partner's, and what they know of everyone else's
+fn boards(game: &Game, player: usize) -> Vec<(String, Vec<String>)> {
+    let mode = game.rules().mode;
+    let mut boards = vec![(
+        format!("You, player {}", player + 1),
+        draw(game.board(player), true),
+    )];
+    for other in (0..game.len()).filter(|other| *other != player) {
+        let status = if game.is_out(other) {
+            "out".to_string()
+        } else {
+            format!("{} afloat", game.board(other).afloat())
+        };
+        let partner = mode.team(other) == mode.team(player);
+        let title = if partner {
+            format!("Partner {} ({status})", other + 1)
+        } else {
+            format!("Player {} ({status})", other + 1)
+        };
+        boards.push((title, draw(game.board(other), partner)));
+    }
+    boards
+}
+
+/// Everything `player` can see, one board after another
+fn view(game: &Game, player: usize) -> String {
+    let boards = boards(game, player);
+    let mut out = String::from(CLEAR);
+    if let Some(left) = game.board(player).clock.remaining() {
+        out.push_str(&format!("Your time: {}\n", Minutes(left)));
+    }
+    // three boards a row fits an 80 column terminal
+    for row in boards.chunks(3) {
+        for (title, _lines) in row {
+            out.push_str(&format!("  {title:<24}"));
+        }
+        out.push('\n');
+        for line in 0..11 {
+            for (_title, lines) in row {
+                out.push_str(&lines[line]);
+                out.push_str("    ");
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The screen for picking a fleet
+fn placing(board: &Board) -> String {
+    let mut out = String::from(CLEAR);
+    for line in draw(board, true) {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out.push_str("\nPress enter to play with these ships, or type r for another layout.\n> ");
+    out
+}
+
+/// Clears the screen and goes back to the top
+const CLEAR: &str = "\u{1b}[2J\u{1b}[H";
+
+/// A board as text, with its fleet showing if `show_ships`
+fn draw(board: &Board, show_ships: bool) -> Vec<String> {
+    let mut lines = Vec::with_capacity(11);
+    lines.push("  1 2 3 4 5 6 7 8 9 10".to_string());
+    for y in 0..10 {
+        let mut line = format!("{} ", char::from(b'A' + y));
+        for x in 0..10 {
+            let cell = Cell::new(x, y.into());
+            let ship = show_ships && board.ships.ship_in(cell).is_some();
+            let land = board.ships.rules().terrain.is_land(cell);
+            let glyph = match board.shot(&cell) {
+                Shot::Hit(_) => "><".red().to_string(),
+                Shot::Miss => "><".white().to_string(),
+                Shot::Empty => "  ".to_string(),
+            };
+            line.push_str(&if ship {
+                glyph.on_bright_black().to_string()
+            } else if land {
+                glyph.on_green().to_string()
+            } else {
+                glyph.on_blue().to_string()
+            });
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Splits off who a player is firing at, like the 3 in `3 B7`. It can be
+/// left out when there's only the one opponent.
+fn order<'a>(line: &'a str, opponents: &[usize]) -> Result<(usize, &'a str), String> {
+    let (first, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    match first.parse::<usize>() {
+        Ok(number) => number
+            .checked_sub(1)
+            .filter(|player| opponents.contains(player))
+            .map(|player| (player, rest))
+            .ok_or_else(|| format!("You can't fire at player {number}.")),
+        Err(_) => match opponents {
+            [only] => Ok((*only, line)),
+            _ => Err("Say who you're firing at first, like 3 B7.".to_string()),
+        },
+    }
+}
+
+/// How many shots the current player gets at `defender`
+fn shots(game: &Game, defender: usize) -> usize {
+    game.rules()
+        .salvo
+        .shots(game.board(game.current()))
+        .min(game.board(defender).unfired())
+        .max(1)
+}
+
+/// Reads the cells a player typed, which should be `shots` of them
+fn aim(line: &str, shots: usize) -> Result<Vec<Cell>, String> {
+    let cells = line
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<Cell>, _>>()
+        .map_err(|e| crate::Error::InvalidCell(e).to_string())?;
+    if cells.len() != shots {
+        return Err(format!("Fire {shots} shots, not {}.", cells.len()));
+    }
+    Ok(cells)
+}
+
+/// What `player` firing at `defender` did, told so anyone can read it
+fn fired(board: &Board, player: usize, defender: usize, outcome: &[(Cell, Shot)]) -> String {
+    let shots: Vec<String> = outcome
+        .iter()
+        .map(|(cell, shot)| match board.report(shot) {
+            Report::Sunk(kind) => format!("{cell} sunk a {kind}!"),
+            Report::Hit(Some(kind)) => format!("{cell} hit a {kind}"),
+            Report::Hit(None) => format!("{cell} hit"),
+            Report::Miss | Report::Nothing => format!("{cell} missed"),
+        })
+        .collect();
+    format!(
+        "Player {} fired at player {}: {}.",
+        player + 1,
+        defender + 1,
+        shots.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aim_reads_a_volley() {
+        let cells = aim("B7, c3 J10", 3).unwrap();
+        assert_eq!(
+            cells,
+            vec![Cell::new(6, 1), Cell::new(2, 2), Cell::new(9, 9)]
+        );
+    }
+
+    #[test]
+    fn teams_see_their_partners_fleet() {
+        use rand::SeedableRng;
+        let rules = Rules {
+            mode: crate::rules::Mode::Teams,
+            ..Rules::default()
+        };
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(7);
+        let boards = (0..4)
+            .map(|_| Board::new(crate::ai::random_fleet(&rules, &mut rng).unwrap()))
+            .collect();
+        let game = Game::new(&rules, boards);
+        // player 2 is on player 0's team, 1 and 3 aren't
+        let seen = super::boards(&game, 0);
+        let titles: Vec<&str> = seen.iter().map(|(title, _lines)| title.as_str()).collect();
+        assert_eq!(
+            titles,
+            [
+                "You, player 1",
+                "Player 2 (5 afloat)",
+                "Partner 3 (5 afloat)",
+                "Player 4 (5 afloat)"
+            ]
+        );
+        assert_eq!(seen[0].1, draw(game.board(0), true));
+        assert_eq!(seen[1].1, draw(game.board(1), false));
+        assert_eq!(seen[2].1, draw(game.board(2), true));
+        assert_eq!(seen[3].1, draw(game.board(3), false));
+        // and player 1 sees the other way round
+        let seen = super::boards(&game, 1);
+        assert_eq!(seen[1].0, "Player 1 (5 afloat)");
+        assert_eq!(seen[1].1, draw(game.board(0), false));
+        assert_eq!(seen[3].0, "Partner 4 (5 afloat)");
+        assert_eq!(seen[3].1, draw(game.board(3), true));
+    }
+
+    #[test]
+    fn order_picks_an_opponent() {
+        assert_eq!(order("3 B7", &[1, 2]), Ok((2, "B7")));
+        assert_eq!(order("B7", &[2]), Ok((2, "B7")));
+        assert!(order("B7", &[1, 2]).is_err());
+        assert!(order("1 B7", &[1, 2]).is_err());
+        assert!(order("0 B7", &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn aim_wants_every_shot() {
+        assert!(aim("B7", 2).is_err());
+        assert!(aim("B7 K1", 2).is_err());
+        assert!(aim("", 1).is_err());
+    }
+}
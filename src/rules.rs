@@ -0,0 +1,258 @@
+use crate::{
+    board::Board,
+    cell::Cell,
+    clock::{TimeControl, Timeout},
+    ship::Fleet,
+    terrain::Terrain,
+    weapon::Arsenal,
+};
+
+/// Everything that can be tweaked about a game before it starts
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+// rules saved before a setting existed get its default
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Rules {
+    /// How many players, and who plays against whom
+    pub mode: Mode,
+    pub fleet: Fleet,
+    pub salvo: Salvo,
+    pub placement: Placement,
+    pub geometry: Geometry,
+    /// Special weapons each player starts with
+    pub arsenal: Arsenal,
+    /// Players may move an undamaged ship instead of firing
+    pub moving_ships: bool,
+    /// Ships may also be placed diagonally
+    pub diagonals: bool,
+    /// Islands and reefs that are off limits
+    pub terrain: Terrain,
+    pub clock: TimeControl,
+    /// What happens when a player's clock runs out
+    pub timeout: Timeout,
+    /// How much the attacker is told about what they hit
+    pub disclosure: Disclosure,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mode {
+    /// The classic one on one game
+    #[default]
+    Duel,
+    /// Everyone against everyone, picking a target every turn
+    FreeForAll(usize),
+    /// Two teams of two, who can see their partner's board
+    Teams,
+}
+
+impl Mode {
+    pub const MAX_PLAYERS: usize = 6;
+
+    pub const fn players(self) -> usize {
+        match self {
+            Self::Duel => 2,
+            Self::FreeForAll(players) => players,
+            Self::Teams => 4,
+        }
+    }
+    pub const fn teams(self) -> usize {
+        match self {
+            Self::Duel | Self::Teams => 2,
+            Self::FreeForAll(players) => players,
+        }
+    }
+    /// Which team `player` is on. Teams take turns in order, so partners
+    /// sit across from each other.
+    pub const fn team(self, player: usize) -> usize {
+        match self {
+            Self::Duel | Self::FreeForAll(_) => player,
+            Self::Teams => player % 2,
+        }
+    }
+    pub fn next(&mut self) {
+        *self = match self {
+            Self::Duel => Self::FreeForAll(3),
+            Self::FreeForAll(players) if *players >= Self::MAX_PLAYERS => Self::Teams,
+            Self::FreeForAll(players) => Self::FreeForAll(*players + 1),
+            Self::Teams => Self::Duel,
+        };
+    }
+    pub fn prev(&mut self) {
+        *self = match self {
+            Self::Duel => Self::Teams,
+            Self::FreeForAll(players) if *players <= 3 => Self::Duel,
+            Self::FreeForAll(players) => Self::FreeForAll(*players - 1),
+            Self::Teams => Self::FreeForAll(Self::MAX_PLAYERS),
+        };
+    }
+}
+
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Duel => f.write_str("1 vs 1"),
+            Self::FreeForAll(players) => write!(f, "Free-for-all, {players} players"),
+            Self::Teams => f.write_str("2 vs 2"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Salvo {
+    /// One shot per turn, the standard game
+    #[default]
+    Off,
+    /// One shot per ship the attacker still has afloat
+    PerShip,
+    /// The same number of shots every turn
+    Fixed(usize),
+}
+
+impl Salvo {
+    /// The most shots a fixed salvo can be set to
+    const MAX_FIXED: usize = 5;
+
+    /// How many cells `attacker` gets to pick this turn
+    pub fn shots(&self, attacker: &Board) -> usize {
+        match self {
+            Self::Off => 1,
+            Self::PerShip => attacker.afloat(),
+            Self::Fixed(shots) => *shots,
+        }
+    }
+    pub fn next(&mut self) {
+        *self = match self {
+            Self::Off => Self::PerShip,
+            Self::PerShip => Self::Fixed(2),
+            Self::Fixed(shots) if *shots < Self::MAX_FIXED => Self::Fixed(*shots + 1),
+            Self::Fixed(_) => Self::Off,
+        };
+    }
+    pub fn prev(&mut self) {
+        *self = match self {
+            Self::Off => Self::Fixed(Self::MAX_FIXED),
+            Self::PerShip => Self::Off,
+            Self::Fixed(shots) if *shots > 2 => Self::Fixed(*shots - 1),
+            Self::Fixed(_) => Self::PerShip,
+        };
+    }
+}
+
+impl std::fmt::Display for Salvo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Off => f.write_str("Off"),
+            Self::PerShip => f.write_str("One per ship afloat"),
+            Self::Fixed(shots) => write!(f, "{shots} per turn"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Placement {
+    /// Ships may be right next to each other
+    #[default]
+    Touching,
+    /// Ships need at least one cell of water between them, diagonals included
+    NoTouching,
+}
+
+impl Placement {
+    pub fn next(&mut self) {
+        *self = match self {
+            Self::Touching => Self::NoTouching,
+            Self::NoTouching => Self::Touching,
+        };
+    }
+}
+
+impl std::fmt::Display for Placement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Touching => f.write_str("Ships may touch"),
+            Self::NoTouching => f.write_str("Ships may not touch"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Disclosure {
+    /// Hits name the ship, and sinks are announced
+    #[default]
+    Full,
+    /// Only hit or miss. What was sunk comes out when the game ends.
+    HitsOnly,
+    /// Hits name the ship, but not whether it sank
+    ClassOnly,
+}
+
+impl Disclosure {
+    pub fn next(&mut self) {
+        *self = match self {
+            Self::Full => Self::HitsOnly,
+            Self::HitsOnly => Self::ClassOnly,
+            Self::ClassOnly => Self::Full,
+        };
+    }
+}
+
+impl std::fmt::Display for Disclosure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Full => f.write_str("Hits and sinks"),
+            Self::HitsOnly => f.write_str("Hit or miss only"),
+            Self::ClassOnly => f.write_str("Ship class only"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Geometry {
+    /// The edges of the board are the edges of the world
+    #[default]
+    Flat,
+    /// Leaving one edge of the board brings you back in on the opposite one,
+    /// and ships may lie across edges
+    Torus,
+}
+
+impl Geometry {
+    /// The cell `(dx, dy)` away from `cell`, or `None` if that's off the board
+    pub fn offset(self, cell: Cell, dx: isize, dy: isize) -> Option<Cell> {
+        match self {
+            Self::Flat => {
+                let x = cell.x().checked_add_signed(dx).filter(|x| *x < 10)?;
+                let y = cell.y().checked_add_signed(dy).filter(|y| *y < 10)?;
+                Some(Cell::new(x, y))
+            }
+            Self::Torus => {
+                let mut out = cell;
+                out += (
+                    dx.rem_euclid(10).unsigned_abs(),
+                    dy.rem_euclid(10).unsigned_abs(),
+                );
+                Some(out)
+            }
+        }
+    }
+    pub fn next(&mut self) {
+        *self = match self {
+            Self::Flat => Self::Torus,
+            Self::Torus => Self::Flat,
+        };
+    }
+}
+
+impl std::fmt::Display for Geometry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Flat => f.write_str("Flat"),
+            Self::Torus => f.write_str("Torus (edges wrap)"),
+        }
+    }
+}
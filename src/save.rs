@@ -0,0 +1,135 @@
+//! Saving and loading anything serializable, as JSON or a compact binary
+//! form. Both start with a format version. JSON from a newer version still
+//! loads as long as it only added fields, binary never does.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::Error;
+
+/// Bump this whenever a saved type changes in a way old code can't read
+pub const VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Saving<'a, T> {
+    version: u32,
+    data: &'a T,
+}
+
+#[derive(Deserialize)]
+struct Loading<T> {
+    version: u32,
+    data: T,
+}
+
+/// Just the version, so newer saves can be turned away before anything else
+/// fails to parse
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+pub fn to_json<T: Serialize>(data: &T) -> Result<String, Error> {
+    Ok(serde_json::to_string(&Saving {
+        version: VERSION,
+        data,
+    })?)
+}
+
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, Error> {
+    let Version { version } = serde_json::from_str(json)?;
+    if version > VERSION {
+        // unknown fields are skipped, so this might still work
+        if let Ok(loaded) = serde_json::from_str::<Loading<T>>(json) {
+            return Ok(loaded.data);
+        }
+        return Err(Error::UnsupportedVersion(version));
+    }
+    Ok(serde_json::from_str::<Loading<T>>(json)?.data)
+}
+
+pub fn to_bytes<T: Serialize>(data: &T) -> Result<Vec<u8>, Error> {
+    Ok(postcard::to_allocvec(&Saving {
+        version: VERSION,
+        data,
+    })?)
+}
+
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+    let (version, _rest): (u32, _) = postcard::take_from_bytes(bytes)?;
+    if version > VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    let loaded: Loading<T> = postcard::from_bytes(bytes)?;
+    debug_assert_eq!(loaded.version, version);
+    Ok(loaded.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bitboard::BitBoard, board::Board, event::Action, game::Game, rules::Rules,
+        ship::ShipSetBuilder,
+    };
+
+    fn game() -> Game {
+        let rules = Rules::default();
+        let boards = (0..2)
+            .map(|seed| {
+                let mut fleet = ShipSetBuilder::new(&rules);
+                assert!(fleet.randomize(seed));
+                Board::new(fleet.build().unwrap())
+            })
+            .collect();
+        let mut game = Game::new(&rules, boards);
+        for _ in 0..5 {
+            let defender = game.opponents()[0];
+            let cell = game.board(defender).targets()[0];
+            game.act(defender, Action::Volley(vec![cell])).unwrap();
+        }
+        game
+    }
+
+    fn assert_same(loaded: &Game, game: &Game) {
+        assert_eq!(loaded.log(), game.log());
+        assert_eq!(loaded.current(), game.current());
+        for player in 0..game.len() {
+            assert_eq!(
+                BitBoard::from(loaded.board(player)),
+                BitBoard::from(game.board(player))
+            );
+        }
+    }
+
+    #[test]
+    fn game_round_trips_as_json() {
+        let game = game();
+        let loaded: Game = from_json(&to_json(&game).unwrap()).unwrap();
+        assert_same(&loaded, &game);
+    }
+
+    #[test]
+    fn game_round_trips_as_bytes() {
+        let game = game();
+        let loaded: Game = from_bytes(&to_bytes(&game).unwrap()).unwrap();
+        assert_same(&loaded, &game);
+    }
+
+    #[test]
+    fn bad_player_index_is_an_error() {
+        let mut json: serde_json::Value = serde_json::from_str(&to_json(&game()).unwrap()).unwrap();
+        json["data"]["log"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({ "Resigned": { "player": 7 } }));
+        let error = from_json::<Game>(&json.to_string()).unwrap_err();
+        assert!(error.to_string().contains("isn't in the game"), "{error}");
+    }
+
+    #[test]
+    fn impossible_player_count_is_an_error() {
+        let mut json: serde_json::Value = serde_json::from_str(&to_json(&game()).unwrap()).unwrap();
+        json["data"]["rules"]["mode"] = serde_json::json!({ "FreeForAll": 0 });
+        assert!(from_json::<Game>(&json.to_string()).is_err());
+    }
+}
@@ -1,68 +1,270 @@
+mod problem;
 mod set;
+mod shape;
 mod state;
 
+pub use problem::{Edge, Problem};
 pub use set::{ShipSet, ShipSetBuilder};
+pub use shape::Shape;
 pub use state::ShipState;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ShipRotation {
     Up,
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
 impl ShipRotation {
+    /// Every rotation, the straight ones first
+    pub const ALL: [Self; 8] = [
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::UpLeft,
+        Self::UpRight,
+        Self::DownLeft,
+        Self::DownRight,
+    ];
+
+    /// Quarter turn clockwise
     pub fn next(&mut self) {
         *self = match self {
             Self::Up => Self::Right,
             Self::Right => Self::Down,
             Self::Down => Self::Left,
             Self::Left => Self::Up,
+            Self::UpRight => Self::DownRight,
+            Self::DownRight => Self::DownLeft,
+            Self::DownLeft => Self::UpLeft,
+            Self::UpLeft => Self::UpRight,
         };
     }
+    /// Quarter turn counterclockwise
     pub fn prev(&mut self) {
+        for _ in 0..3 {
+            self.next();
+        }
+    }
+    /// Eighth turn clockwise, through the diagonals
+    pub fn next_eighth(&mut self) {
         *self = match self {
-            Self::Up => Self::Left,
-            Self::Right => Self::Up,
-            Self::Down => Self::Right,
-            Self::Left => Self::Down,
+            Self::Up => Self::UpRight,
+            Self::UpRight => Self::Right,
+            Self::Right => Self::DownRight,
+            Self::DownRight => Self::Down,
+            Self::Down => Self::DownLeft,
+            Self::DownLeft => Self::Left,
+            Self::Left => Self::UpLeft,
+            Self::UpLeft => Self::Up,
         };
     }
+    /// Eighth turn counterclockwise, through the diagonals
+    pub fn prev_eighth(&mut self) {
+        for _ in 0..7 {
+            self.next_eighth();
+        }
+    }
+    pub const fn is_diagonal(self) -> bool {
+        matches!(
+            self,
+            Self::UpLeft | Self::UpRight | Self::DownLeft | Self::DownRight
+        )
+    }
+    /// One cell in this direction, as `(dx, dy)`
+    pub const fn step(self) -> (isize, isize) {
+        match self {
+            Self::Up => (0, -1),
+            Self::Down => (0, 1),
+            Self::Left => (-1, 0),
+            Self::Right => (1, 0),
+            Self::UpLeft => (-1, -1),
+            Self::UpRight => (1, -1),
+            Self::DownLeft => (-1, 1),
+            Self::DownRight => (1, 1),
+        }
+    }
+}
+
+impl std::fmt::Display for ShipRotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Up => f.write_str("up"),
+            Self::Down => f.write_str("down"),
+            Self::Left => f.write_str("left"),
+            Self::Right => f.write_str("right"),
+            Self::UpLeft => f.write_str("up-left"),
+            Self::UpRight => f.write_str("up-right"),
+            Self::DownLeft => f.write_str("down-left"),
+            Self::DownRight => f.write_str("down-right"),
+        }
+    }
+}
+
+/// Ways a ship can move once the game has started
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Motion {
+    /// One cell towards the bow
+    Forward,
+    /// One cell towards the stern
+    Backward,
+    /// A quarter turn counterclockwise around the bow
+    TurnLeft,
+    /// A quarter turn clockwise around the bow
+    TurnRight,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub enum ShipType {
-    AircraftCarrier,
-    Battleship,
-    Destroyer,
-    Submarine,
-    PatrolBoat,
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShipType {
+    name: &'static str,
+    shape: Shape,
+}
+
+/// Ship names are `&'static str`, so loaded ones have to be made to live forever
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SavedShipType {
+    name: String,
+    shape: Shape,
+}
+
+// not derived, serde would want to borrow the name from the input
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ShipType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let saved = SavedShipType::deserialize(deserializer)?;
+        Ok(Self::shaped(intern(saved.name), saved.shape))
+    }
+}
+
+/// Leaks each distinct name once, however many times it's loaded
+#[cfg(feature = "serde")]
+fn intern(name: String) -> &'static str {
+    static NAMES: std::sync::Mutex<Vec<&'static str>> = std::sync::Mutex::new(Vec::new());
+    let mut names = NAMES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some(known) = names.iter().find(|known| **known == name) {
+        return known;
+    }
+    let name: &'static str = Box::leak(name.into_boxed_str());
+    names.push(name);
+    name
 }
 
 impl ShipType {
-    /// Returns `true` if complete
-    pub fn next(&mut self) -> bool {
-        *self = match self {
-            Self::AircraftCarrier => Self::Battleship,
-            Self::Battleship => Self::Destroyer,
-            Self::Destroyer => Self::Submarine,
-            Self::Submarine => Self::PatrolBoat,
-            Self::PatrolBoat => return true,
-        };
-        false
+    pub const AIRCRAFT_CARRIER: Self = Self::new("Aircraft Carrier", 5);
+    pub const BATTLESHIP: Self = Self::new("Battleship", 4);
+    pub const CRUISER: Self = Self::new("Cruiser", 3);
+    pub const DESTROYER: Self = Self::new("Destroyer", 3);
+    pub const SUBMARINE: Self = Self::new("Submarine", 3);
+    pub const PATROL_BOAT: Self = Self::new("Patrol Boat", 2);
+
+    /// A straight ship `length` cells long
+    pub const fn new(name: &'static str, length: usize) -> Self {
+        Self::shaped(name, Shape::line(length))
+    }
+    pub const fn shaped(name: &'static str, shape: Shape) -> Self {
+        Self { name, shape }
+    }
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+    pub const fn shape(&self) -> Shape {
+        self.shape
+    }
+    /// How many cells the ship takes up
+    pub const fn length(&self) -> usize {
+        self.shape.len()
     }
 }
 
 impl std::fmt::Display for ShipType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let name = match self {
-            Self::AircraftCarrier => "Aircraft Carrier",
-            Self::Battleship => "Battleship",
-            Self::Destroyer => "Destroyer",
-            Self::Submarine => "Submarine",
-            Self::PatrolBoat => "Patrol Boat",
-        };
-        f.write_str(name)
+        f.write_str(self.name)
+    }
+}
+
+/// The ships each player has to place, in placement order.
+/// Kinds may repeat, e.g. four patrol boats.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fleet {
+    ships: Vec<ShipType>,
+}
+
+impl Fleet {
+    pub const fn new(ships: Vec<ShipType>) -> Self {
+        Self { ships }
+    }
+    /// Carrier, battleship, destroyer, submarine and patrol boat.
+    pub fn classic() -> Self {
+        Self::new(vec![
+            ShipType::AIRCRAFT_CARRIER,
+            ShipType::BATTLESHIP,
+            ShipType::DESTROYER,
+            ShipType::SUBMARINE,
+            ShipType::PATROL_BOAT,
+        ])
+    }
+    /// One 4-long, two 3-long, three 2-long and four 1-long ships.
+    pub fn milton_bradley() -> Self {
+        // names are how layouts and bots tell ships apart, so these can't
+        // share one with a ship of another length
+        let dinghy = ShipType::new("Dinghy", 1);
+        Self::new(vec![
+            ShipType::BATTLESHIP,
+            ShipType::CRUISER,
+            ShipType::CRUISER,
+            ShipType::PATROL_BOAT,
+            ShipType::PATROL_BOAT,
+            ShipType::PATROL_BOAT,
+            dinghy,
+            dinghy,
+            dinghy,
+            dinghy,
+        ])
+    }
+    /// A couple of straight ships and one of each odd shape
+    pub fn shapes() -> Self {
+        Self::new(vec![
+            ShipType::AIRCRAFT_CARRIER,
+            ShipType::BATTLESHIP,
+            ShipType::shaped("Hook", Shape::L),
+            ShipType::shaped("Tee", Shape::T),
+            ShipType::shaped("Sea Fort", Shape::PLUS),
+            ShipType::shaped("Floating Dock", Shape::BLOCK),
+        ])
+    }
+    pub fn ships(&self) -> &[ShipType] {
+        &self.ships
+    }
+    pub fn get(&self, index: usize) -> Option<ShipType> {
+        self.ships.get(index).copied()
+    }
+    pub fn len(&self) -> usize {
+        self.ships.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ships.is_empty()
+    }
+    /// How many cells the whole fleet takes up
+    pub fn cell_count(&self) -> usize {
+        self.ships.iter().map(ShipType::length).sum()
+    }
+}
+
+impl Default for Fleet {
+    fn default() -> Self {
+        Self::classic()
     }
 }
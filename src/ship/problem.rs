@@ -0,0 +1,95 @@
+use crate::cell::Cell;
+
+use super::ShipType;
+
+/// An edge of the board
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl std::fmt::Display for Edge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Top => f.write_str("top"),
+            Self::Bottom => f.write_str("bottom"),
+            Self::Left => f.write_str("left"),
+            Self::Right => f.write_str("right"),
+        }
+    }
+}
+
+/// Something wrong with a fleet layout, and the cells it's wrong on
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Problem {
+    /// Part of the ship is off the board. `cells` is the part that isn't.
+    Overflow {
+        ship: ShipType,
+        edge: Edge,
+        by: usize,
+        cells: Vec<Cell>,
+    },
+    Aground {
+        ship: ShipType,
+        cells: Vec<Cell>,
+    },
+    /// The ship lies diagonally, and either the rules or its shape say it can't
+    Diagonal {
+        ship: ShipType,
+        cells: Vec<Cell>,
+    },
+    Overlap {
+        ship: ShipType,
+        other: ShipType,
+        cells: Vec<Cell>,
+    },
+    /// The ship is right next to another, and the rules say it can't be
+    Touching {
+        ship: ShipType,
+        other: ShipType,
+        cells: Vec<Cell>,
+    },
+    Unplaced {
+        ship: ShipType,
+    },
+}
+
+impl Problem {
+    /// The cells to point at when showing this problem
+    pub fn cells(&self) -> &[Cell] {
+        match self {
+            Self::Overflow { cells, .. }
+            | Self::Aground { cells, .. }
+            | Self::Diagonal { cells, .. }
+            | Self::Overlap { cells, .. }
+            | Self::Touching { cells, .. } => cells,
+            Self::Unplaced { .. } => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // every problem but Unplaced has at least one cell
+        let at = self
+            .cells()
+            .first()
+            .map_or(String::new(), ToString::to_string);
+        match self {
+            Self::Overflow { ship, edge, by, .. } => {
+                write!(f, "{ship} extends past the {edge} edge by {by}")
+            }
+            Self::Aground { ship, .. } => write!(f, "{ship} runs aground at {at}"),
+            Self::Diagonal { ship, .. } if ship.shape().is_line() => {
+                write!(f, "{ship} can't lie diagonally under these rules")
+            }
+            Self::Diagonal { ship, .. } => write!(f, "{ship} is the wrong shape to lie diagonally"),
+            Self::Overlap { ship, other, .. } => write!(f, "{ship} overlaps {other} at {at}"),
+            Self::Touching { ship, other, .. } => write!(f, "{ship} touches {other} at {at}"),
+            Self::Unplaced { ship } => write!(f, "{ship} hasn't been placed yet"),
+        }
+    }
+}
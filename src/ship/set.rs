@@ -1,159 +1,451 @@
 use std::sync::Arc;
 
-use crate::cell::Cell;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
-use super::ShipState;
+use crate::{
+    cell::Cell,
+    error::Error,
+    rules::{Geometry, Placement, Rules},
+};
+
+use super::{Fleet, Problem, ShipRotation, ShipState};
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SavedShipSet", into = "SavedShipSet")
+)]
 pub struct ShipSet {
-    carrier: ShipState,
-    battleship: ShipState,
-    destroyer: ShipState,
-    submarine: ShipState,
-    patrol: ShipState,
+    rules: Rules,
+    ships: Vec<Arc<ShipState>>,
     refs: RawShipBoard,
 }
 
 impl ShipSet {
     pub fn ship_in(&self, cell: Cell) -> Option<ShipState> {
+        self.ref_for(cell).map(|ship| *ship)
+    }
+    pub fn ref_for(&self, cell: Cell) -> Option<Arc<ShipState>> {
+        self.refs[cell.x()][cell.y()].map(|index| self.ships[index].clone())
+    }
+    pub const fn contains_ship(&self, cell: Cell) -> bool {
+        self.refs[cell.x()][cell.y()].is_some()
+    }
+    /// Which ship of the fleet is on `cell`, if any
+    pub const fn index_in(&self, cell: Cell) -> Option<usize> {
         self.refs[cell.x()][cell.y()]
     }
-    pub fn contains_ship(&self, cell: Cell) -> bool {
-        self.ship_in(cell).is_some()
+    pub const fn fleet(&self) -> &Fleet {
+        &self.rules.fleet
+    }
+    /// The rules this set was placed under
+    pub const fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    /// Ships in the same order as the fleet they were placed from
+    pub fn ships(&self) -> impl Iterator<Item = &ShipState> {
+        self.ships.iter().map(AsRef::as_ref)
     }
     pub fn occupied_cells(&self) -> Vec<Cell> {
-        // 17 is the max number of cells we could take up
-        // this hyper-optimizes allocations
-        // because *that's* the expensive operation here
-        let mut out: Vec<Cell> = Vec::with_capacity(17);
-        out.append(&mut self.carrier.occupies());
-        out.append(&mut self.battleship.occupies());
-        out.append(&mut self.destroyer.occupies());
-        out.append(&mut self.submarine.occupies());
-        out.append(&mut self.patrol.occupies());
-
+        // allocate exactly once, because *that's* the expensive operation here
+        let mut out: Vec<Cell> = Vec::with_capacity(self.fleet().cell_count());
+        for ship in &self.ships {
+            out.append(&mut ship.occupies(self.rules.geometry));
+        }
         out
     }
+    /// A builder with every ship of this set already placed
+    pub fn to_builder(&self) -> ShipSetBuilder {
+        ShipSetBuilder {
+            rules: self.rules.clone(),
+            ships: self.ships.iter().map(|ship| Some(**ship)).collect(),
+        }
+    }
+    /// Puts the `index`th ship somewhere else, as long as the set stays valid.
+    /// Returns `false` and leaves the set alone otherwise.
+    pub fn relocate(&mut self, index: usize, ship: ShipState) -> bool {
+        let mut builder = self.to_builder();
+        if !builder.place(index, ship) || !builder.is_valid() {
+            return false;
+        }
+        let geometry = self.rules.geometry;
+        for cell in self.ships[index].occupies(geometry) {
+            self.refs[cell.x()][cell.y()] = None;
+        }
+        for cell in ship.occupies(geometry) {
+            self.refs[cell.x()][cell.y()] = Some(index);
+        }
+        self.ships[index] = Arc::new(ship);
+        true
+    }
+}
+
+/// A ship set as it's saved. `refs` is rebuilt on load, and the layout is
+/// checked against the rules all over again.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedShipSet {
+    rules: Rules,
+    ships: Vec<ShipState>,
+}
+
+#[cfg(feature = "serde")]
+impl From<ShipSet> for SavedShipSet {
+    fn from(set: ShipSet) -> Self {
+        Self {
+            ships: set.ships().copied().collect(),
+            rules: set.rules,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SavedShipSet> for ShipSet {
+    type Error = Error;
+
+    fn try_from(saved: SavedShipSet) -> Result<Self, Self::Error> {
+        let mut builder = ShipSetBuilder::new(&saved.rules);
+        for (index, ship) in saved.ships.into_iter().enumerate() {
+            if !builder.place(index, ship) {
+                return Err(Error::InvalidShipState(Box::new(Problem::Unplaced {
+                    ship: ship.kind(),
+                })));
+            }
+        }
+        builder.build()
+    }
 }
 
-type RawShipBoard = [[Option<ShipState>; 10]; 10];
+type RawShipBoard = [[Option<usize>; 10]; 10];
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct ShipSetBuilder {
-    carrier: Option<ShipState>,
-    battleship: Option<ShipState>,
-    destroyer: Option<ShipState>,
-    submarine: Option<ShipState>,
-    patrol: Option<ShipState>,
+    rules: Rules,
+    ships: Vec<Option<ShipState>>,
 }
 
 impl ShipSetBuilder {
-    pub fn new() -> Self {
-        Self::default()
-    }
-    pub fn build(&self) -> Option<ShipSet> {
-        if !self.is_valid() {
-            return None;
-        }
-        let carrier = self.carrier?;
-        let battleship = self.battleship?;
-        let destroyer = self.destroyer?;
-        let submarine = self.submarine?;
-        let patrol = self.patrol?;
-        // thank you, copy requirement for [None; 10]
-        let mut refs: RawShipBoard = [[None; 10]; 10];
-        for cell in carrier.occupies() {
-            refs[cell.x()][cell.y()] = Some(carrier);
-        }
-        for cell in battleship.occupies() {
-            refs[cell.x()][cell.y()] = Some(battleship);
+    pub fn new(rules: &Rules) -> Self {
+        Self {
+            rules: rules.clone(),
+            ships: vec![None; rules.fleet.len()],
         }
-        for cell in destroyer.occupies() {
-            refs[cell.x()][cell.y()] = Some(destroyer);
-        }
-        for cell in submarine.occupies() {
-            refs[cell.x()][cell.y()] = Some(submarine);
+    }
+    pub fn build(&self) -> Result<ShipSet, Error> {
+        if let Some(problem) = self.problems().into_iter().next() {
+            return Err(Error::InvalidShipState(Box::new(problem)));
         }
-        for cell in patrol.occupies() {
-            refs[cell.x()][cell.y()] = Some(patrol);
+        let ships: Vec<Arc<ShipState>> = self
+            .ships
+            .iter()
+            .zip(self.fleet().ships())
+            .map(|(ship, kind)| {
+                ship.map(Arc::new).ok_or_else(|| {
+                    Error::InvalidShipState(Box::new(Problem::Unplaced { ship: *kind }))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        // thank you, copy requirement for [None; 10]
+        let mut refs: RawShipBoard = [[None; 10]; 10];
+        for (index, ship) in ships.iter().enumerate() {
+            for cell in ship.occupies(self.rules.geometry) {
+                refs[cell.x()][cell.y()] = Some(index);
+            }
         }
-        Some(ShipSet {
-            carrier,
-            battleship,
-            destroyer,
-            submarine,
-            patrol,
+        Ok(ShipSet {
+            rules: self.rules.clone(),
+            ships,
             refs,
         })
     }
     pub fn occupied_cells(&self) -> Vec<Cell> {
-        // 17 is the max number of cells we could take up
-        // this hyper-optimizes allocations
-        // because *that's* the expensive operation here
-        let mut out: Vec<Cell> = Vec::with_capacity(17);
-        if let Some(carrier) = self.carrier {
-            out.append(&mut carrier.occupies());
-        }
-        if let Some(battleship) = self.battleship {
-            out.append(&mut battleship.occupies());
-        }
-        if let Some(destroyer) = self.destroyer {
-            out.append(&mut destroyer.occupies());
-        }
-        if let Some(submarine) = self.submarine {
-            out.append(&mut submarine.occupies());
-        }
-        if let Some(patrol) = self.patrol {
-            out.append(&mut patrol.occupies());
+        // allocate exactly once, because *that's* the expensive operation here
+        let mut out: Vec<Cell> = Vec::with_capacity(self.fleet().cell_count());
+        for ship in self.ships.iter().flatten() {
+            out.append(&mut ship.occupies(self.rules.geometry));
         }
         out
     }
+    /// Cells no ship but the `except`th may use because they border another
+    /// ship. Always empty unless ships may not touch.
+    pub fn buffer_cells(&self, except: usize) -> Vec<Cell> {
+        if self.rules.placement == Placement::Touching {
+            return Vec::new();
+        }
+        self.ships
+            .iter()
+            .enumerate()
+            .filter(|(i, _ship)| *i != except)
+            .filter_map(|(_i, ship)| *ship)
+            .flat_map(|ship| ship.surrounding(self.rules.geometry))
+            .collect()
+    }
     pub fn is_valid(&self) -> bool {
-        if let Some(carrier) = self.carrier {
-            if carrier.overflows() {
-                return false;
+        self.problems().is_empty()
+    }
+    /// Everything wrong with the ships placed so far
+    pub fn problems(&self) -> Vec<Problem> {
+        let geometry = self.rules.geometry;
+        let placed: Vec<ShipState> = self.ships.iter().flatten().copied().collect();
+        let mut out = Vec::new();
+        for ship in &placed {
+            let cells = ship.occupies(geometry);
+            if ship.overflows(geometry) {
+                if let Some((edge, by)) = ship.overhang() {
+                    out.push(Problem::Overflow {
+                        ship: ship.kind(),
+                        edge,
+                        by,
+                        cells: cells.clone(),
+                    });
+                }
             }
-        }
-        if let Some(battleship) = self.battleship {
-            if battleship.overflows() {
-                return false;
+            let aground: Vec<Cell> = cells
+                .iter()
+                .copied()
+                .filter(|cell| self.rules.terrain.is_land(*cell))
+                .collect();
+            if !aground.is_empty() {
+                out.push(Problem::Aground {
+                    ship: ship.kind(),
+                    cells: aground,
+                });
             }
-        }
-        if let Some(destroyer) = self.destroyer {
-            if destroyer.overflows() {
-                return false;
+            // only straight ships can lie diagonally, and only if the rules say so
+            if ship.rotation().is_diagonal()
+                && (!self.rules.diagonals || !ship.kind().shape().is_line())
+            {
+                out.push(Problem::Diagonal {
+                    ship: ship.kind(),
+                    cells,
+                });
             }
         }
-        if let Some(submarine) = self.submarine {
-            if submarine.overflows() {
-                return false;
+        // later ships are the ones doing the overlapping
+        for (i, earlier) in placed.iter().enumerate() {
+            let earlier_cells = earlier.occupies(geometry);
+            let buffer = if self.rules.placement == Placement::NoTouching {
+                earlier.surrounding(geometry)
+            } else {
+                Vec::new()
+            };
+            for later in &placed[i + 1..] {
+                let later_cells = later.occupies(geometry);
+                let mut shared: Vec<Cell> = later_cells
+                    .iter()
+                    .copied()
+                    .filter(|cell| earlier_cells.contains(cell))
+                    .collect();
+                // diagonal ships can cross like an X without sharing a cell
+                if shared.is_empty()
+                    && earlier.rotation().is_diagonal()
+                    && later.rotation().is_diagonal()
+                {
+                    shared = crossing(later, &earlier_cells, geometry);
+                }
+                if !shared.is_empty() {
+                    out.push(Problem::Overlap {
+                        ship: later.kind(),
+                        other: earlier.kind(),
+                        cells: shared,
+                    });
+                    continue;
+                }
+                let touching: Vec<Cell> = later_cells
+                    .into_iter()
+                    .filter(|cell| buffer.contains(cell))
+                    .collect();
+                if !touching.is_empty() {
+                    out.push(Problem::Touching {
+                        ship: later.kind(),
+                        other: earlier.kind(),
+                        cells: touching,
+                    });
+                }
             }
         }
-        if let Some(patrol) = self.patrol {
-            if patrol.overflows() {
-                return false;
+        out
+    }
+    /// Throws every ship of the fleet somewhere random but valid, always the
+    /// same way for the same seed. Every valid layout is equally likely.
+    /// Returns `false` and clears the builder if no layout turned up, which
+    /// only really happens when the fleet doesn't fit.
+    pub fn randomize(&mut self, seed: u64) -> bool {
+        // give up eventually, some fleets just don't fit
+        const ATTEMPTS: usize = 100_000;
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let geometry = self.rules.geometry;
+        let no_touching = self.rules.placement == Placement::NoTouching;
+        for _ in 0..ATTEMPTS {
+            self.ships.fill(None);
+            // cells later ships can't use, a much cheaper check than is_valid
+            let mut taken = [[false; 10]; 10];
+            // throwing all ships at once and starting over on any clash keeps
+            // layouts uniform, and bailing out at the first clash is the same
+            // as checking the whole fleet at the end
+            let placed = (0..self.ships.len()).all(|index| {
+                let ship = self.random_ship(index, &mut rng);
+                let cells = ship.occupies(geometry);
+                let fits = !ship.overflows(geometry)
+                    && cells.iter().all(|cell| {
+                        !taken[cell.x()][cell.y()] && !self.rules.terrain.is_land(*cell)
+                    });
+                if !fits {
+                    return false;
+                }
+                let buffer = if no_touching {
+                    ship.surrounding(geometry)
+                } else {
+                    Vec::new()
+                };
+                for cell in cells.iter().chain(&buffer) {
+                    taken[cell.x()][cell.y()] = true;
+                }
+                self.ships[index] = Some(ship);
+                true
+            });
+            if placed && self.is_valid() {
+                return true;
             }
         }
-        let cells = self.occupied_cells();
-        let mut uniq = std::collections::HashSet::new();
-        cells.into_iter().all(move |x| uniq.insert(x))
+        self.ships.fill(None);
+        false
+    }
+    fn random_ship(&self, index: usize, rng: &mut ChaCha8Rng) -> ShipState {
+        let kind = self.rules.fleet.ships()[index];
+        let rotations = if self.rules.diagonals && kind.shape().is_line() {
+            &ShipRotation::ALL[..]
+        } else {
+            &ShipRotation::ALL[..4]
+        };
+        let pos = Cell::new(rng.gen_range(0..10), rng.gen_range(0..10));
+        let rot = rotations[rng.gen_range(0..rotations.len())];
+        ShipState::new(pos, rot, kind).with_mirrored(rng.gen())
     }
     pub fn contains_ship(&self, cell: Cell) -> bool {
-        self.occupied_cells().contains(&cell)
+        self.ship_in(cell).is_some()
+    }
+    /// The last placed ship on `cell`, if any
+    pub fn ship_in(&self, cell: Cell) -> Option<ShipState> {
+        self.ships
+            .iter()
+            .rev()
+            .flatten()
+            .find(|ship| ship.occupies(self.rules.geometry).contains(&cell))
+            .copied()
+    }
+    pub const fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    pub const fn fleet(&self) -> &Fleet {
+        &self.rules.fleet
+    }
+    /// Places the `index`th ship of the fleet.
+    /// Returns `false` if the fleet has no such ship of that kind.
+    pub fn place(&mut self, index: usize, ship: ShipState) -> bool {
+        if self.fleet().get(index) != Some(ship.kind()) {
+            return false;
+        }
+        self.ships[index] = Some(ship);
+        true
+    }
+    /// Takes the `index`th ship back off the board
+    pub fn remove(&mut self, index: usize) -> Option<ShipState> {
+        self.ships.get_mut(index)?.take()
     }
-    pub fn carrier(&mut self, ship: ShipState) {
-        self.carrier = Some(ship);
+    /// Where the `index`th ship is, if it's been placed
+    pub fn placed(&self, index: usize) -> Option<ShipState> {
+        self.ships.get(index).copied().flatten()
     }
-    pub fn battleship(&mut self, ship: ShipState) {
-        self.battleship = Some(ship);
+    /// Which ship of the fleet is on `cell`, the last placed one if several are
+    pub fn index_in(&self, cell: Cell) -> Option<usize> {
+        self.ships.iter().rposition(|ship| {
+            ship.is_some_and(|ship| ship.occupies(self.rules.geometry).contains(&cell))
+        })
     }
-    pub fn destroyer(&mut self, ship: ShipState) {
-        self.destroyer = Some(ship);
+    /// Returns `true` once every ship of the fleet has been placed
+    pub fn is_complete(&self) -> bool {
+        self.ships.iter().all(Option::is_some)
     }
-    pub fn submarine(&mut self, ship: ShipState) {
-        self.submarine = Some(ship);
+}
+
+impl Default for ShipSetBuilder {
+    fn default() -> Self {
+        Self::new(&Rules::default())
     }
-    pub fn patrol(&mut self, ship: ShipState) {
-        self.patrol = Some(ship);
+}
+
+/// The two cells of diagonal `ship` that pass between two diagonal
+/// neighbours in `other`, if it crosses it anywhere
+fn crossing(ship: &ShipState, other: &[Cell], geometry: Geometry) -> Vec<Cell> {
+    let (dx, dy) = ship.rotation().step();
+    let cells = ship.occupies(geometry);
+    cells
+        .iter()
+        .find_map(|cell| {
+            let next = geometry.offset(*cell, dx, dy)?;
+            let corners = [
+                geometry.offset(*cell, dx, 0)?,
+                geometry.offset(*cell, 0, dy)?,
+            ];
+            (cells.contains(&next) && corners.iter().all(|corner| other.contains(corner)))
+                .then(|| vec![*cell, next])
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ship::ShipType;
+
+    fn builder(geometry: Geometry) -> ShipSetBuilder {
+        ShipSetBuilder::new(&Rules {
+            fleet: Fleet::new(vec![ShipType::PATROL_BOAT; 2]),
+            diagonals: true,
+            geometry,
+            ..Rules::default()
+        })
+    }
+
+    fn boat(pos: &str, rot: ShipRotation) -> ShipState {
+        ShipState::new(pos.parse().unwrap(), rot, ShipType::PATROL_BOAT)
+    }
+
+    #[test]
+    fn diagonals_crossing_like_an_x_overlap() {
+        for geometry in [Geometry::Flat, Geometry::Torus] {
+            let mut ships = builder(geometry);
+            // B2 to C3, and C2 to B3
+            let down = boat("B2", ShipRotation::DownRight);
+            let up = boat("C2", ShipRotation::UpRight);
+            assert_eq!(
+                down.occupies(geometry),
+                ["B2".parse().unwrap(), "C3".parse().unwrap()]
+            );
+            assert!(up.occupies(geometry).contains(&"B3".parse().unwrap()));
+            ships.place(0, down);
+            ships.place(1, up);
+            assert!(matches!(ships.problems()[..], [Problem::Overlap { .. }]));
+        }
+    }
+
+    #[test]
+    fn diagonals_crossing_over_the_edge_of_a_torus_overlap() {
+        let mut ships = builder(Geometry::Torus);
+        ships.place(0, boat("J10", ShipRotation::DownRight));
+        ships.place(1, boat("A10", ShipRotation::UpRight));
+        assert!(matches!(ships.problems()[..], [Problem::Overlap { .. }]));
+    }
+
+    #[test]
+    fn parallel_diagonals_dont_overlap() {
+        let mut ships = builder(Geometry::Flat);
+        ships.place(0, boat("B2", ShipRotation::DownRight));
+        ships.place(1, boat("B3", ShipRotation::DownRight));
+        assert!(ships.problems().is_empty());
+        assert!(ships.build().is_ok());
     }
 }
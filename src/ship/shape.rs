@@ -0,0 +1,134 @@
+use super::ShipRotation;
+
+/// The cells a ship covers, as offsets from its bow. Shapes are written
+/// as they'd be placed facing `ShipRotation::Down`, so `y` runs along the
+/// ship and `x` across it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Vec<(i8, i8)>", into = "Vec<(i8, i8)>")
+)]
+pub struct Shape {
+    cells: [(i8, i8); Self::MAX_CELLS],
+    len: usize,
+}
+
+impl TryFrom<Vec<(i8, i8)>> for Shape {
+    type Error = String;
+
+    fn try_from(cells: Vec<(i8, i8)>) -> Result<Self, Self::Error> {
+        if cells.len() > Self::MAX_CELLS {
+            return Err(format!(
+                "ships can't have more than {} cells",
+                Self::MAX_CELLS
+            ));
+        }
+        Ok(Self::new(&cells))
+    }
+}
+
+impl From<Shape> for Vec<(i8, i8)> {
+    fn from(shape: Shape) -> Self {
+        shape.cells().to_vec()
+    }
+}
+
+impl Shape {
+    /// Most cells a single ship can take up
+    pub const MAX_CELLS: usize = 10;
+
+    /// ```text
+    /// #
+    /// #
+    /// ##
+    /// ```
+    pub const L: Self = Self::new(&[(0, 0), (0, 1), (0, 2), (1, 2)]);
+    /// ```text
+    /// ###
+    ///  #
+    /// ```
+    pub const T: Self = Self::new(&[(0, 0), (-1, 0), (1, 0), (0, 1)]);
+    /// ```text
+    ///  #
+    /// ###
+    ///  #
+    /// ```
+    pub const PLUS: Self = Self::new(&[(0, 0), (0, -1), (-1, 0), (1, 0), (0, 1)]);
+    /// ```text
+    /// ##
+    /// ##
+    /// ```
+    pub const BLOCK: Self = Self::new(&[(0, 0), (1, 0), (0, 1), (1, 1)]);
+
+    /// # Panics
+    /// If there are more than [`Self::MAX_CELLS`] cells
+    pub const fn new(cells: &[(i8, i8)]) -> Self {
+        assert!(cells.len() <= Self::MAX_CELLS, "too many cells for a ship");
+        let mut out = [(0, 0); Self::MAX_CELLS];
+        let mut i = 0;
+        while i < cells.len() {
+            out[i] = cells[i];
+            i += 1;
+        }
+        Self {
+            cells: out,
+            len: cells.len(),
+        }
+    }
+    /// A straight ship `length` cells long
+    ///
+    /// # Panics
+    /// If `length` is more than [`Self::MAX_CELLS`]
+    pub const fn line(length: usize) -> Self {
+        assert!(length <= Self::MAX_CELLS, "too many cells for a ship");
+        let mut cells = [(0, 0); Self::MAX_CELLS];
+        let mut i = 0;
+        while i < length {
+            // fits, MAX_CELLS is tiny
+            #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+            {
+                cells[i] = (0, i as i8);
+            }
+            i += 1;
+        }
+        Self { cells, len: length }
+    }
+    pub fn cells(&self) -> &[(i8, i8)] {
+        &self.cells[..self.len]
+    }
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Returns `true` for straight ships, which are the only ones that can
+    /// lie diagonally
+    pub fn is_line(&self) -> bool {
+        self.cells()
+            .iter()
+            .enumerate()
+            .all(|(i, cell)| usize::try_from(cell.1) == Ok(i) && cell.0 == 0)
+    }
+    /// The offsets of each cell once the ship faces `rot`, flipped
+    /// side to side first if `mirrored`
+    pub fn oriented(
+        self,
+        rot: ShipRotation,
+        mirrored: bool,
+    ) -> impl Iterator<Item = (isize, isize)> {
+        let (ax, ay) = rot.step();
+        (0..self.len).map(move |i| {
+            let (x, y) = self.cells[i];
+            let x = if mirrored {
+                -isize::from(x)
+            } else {
+                isize::from(x)
+            };
+            let y = isize::from(y);
+            // `y` runs along the facing, `x` across it
+            (x * ay + y * ax, y * ay - x * ax)
+        })
+    }
+}
@@ -1,63 +1,137 @@
-use crate::cell::Cell;
+use crate::{cell::Cell, rules::Geometry};
 
-use super::{ShipRotation, ShipType};
+use super::{Edge, Motion, ShipRotation, ShipType};
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ShipState {
     rot: ShipRotation,
     pos: Cell,
     kind: ShipType,
+    #[cfg_attr(feature = "serde", serde(default))]
+    mirrored: bool,
 }
 
 impl ShipState {
     pub const fn new(pos: Cell, rot: ShipRotation, kind: ShipType) -> Self {
-        Self { rot, pos, kind }
+        Self {
+            rot,
+            pos,
+            kind,
+            mirrored: false,
+        }
+    }
+    /// The same ship, flipped side to side if `mirrored`
+    pub const fn with_mirrored(mut self, mirrored: bool) -> Self {
+        self.mirrored = mirrored;
+        self
+    }
+    pub const fn is_mirrored(&self) -> bool {
+        self.mirrored
+    }
+    /// Where the bow is
+    pub const fn pos(&self) -> Cell {
+        self.pos
     }
     pub const fn kind(&self) -> ShipType {
         self.kind
     }
     pub const fn length(&self) -> usize {
-        match self.kind {
-            ShipType::AircraftCarrier => 5,
-            ShipType::Battleship => 4,
-            ShipType::Destroyer | ShipType::Submarine => 3,
-            ShipType::PatrolBoat => 2,
-        }
+        self.kind.length()
+    }
+    pub const fn rotation(&self) -> ShipRotation {
+        self.rot
     }
-    pub const fn overflows(&self) -> bool {
-        match self.rot {
-            ShipRotation::Up => self.pos.y() < self.length() - 1,
-            ShipRotation::Down => self.pos.y() + self.length() > 10,
-            ShipRotation::Left => self.pos.x() < self.length() - 1,
-            ShipRotation::Right => self.pos.x() + self.length() > 10,
+    /// Where this ship ends up after `motion`, or `None` if its bow would
+    /// leave the board
+    pub fn moved(&self, motion: Motion, geometry: Geometry) -> Option<Self> {
+        // the ship trails behind the bow in the direction of `rot`
+        let (dx, dy) = self.rot.step();
+        let mut out = *self;
+        match motion {
+            Motion::Forward => out.pos = geometry.offset(self.pos, -dx, -dy)?,
+            Motion::Backward => out.pos = geometry.offset(self.pos, dx, dy)?,
+            Motion::TurnLeft => out.rot.prev(),
+            Motion::TurnRight => out.rot.next(),
         }
+        Some(out)
     }
-    pub fn occupies(&self) -> Vec<Cell> {
-        let mut occupies: Vec<Cell> = Vec::with_capacity(self.length());
-        for i in 0..self.length() {
-            let mut pos_x = self.pos.x();
-            let mut pos_y = self.pos.y();
-            match self.rot {
-                ShipRotation::Up => {
-                    if pos_y < i {
-                        pos_y += 10;
-                    }
-                }
-                ShipRotation::Left => {
-                    if pos_x < i {
-                        pos_x += 10;
+    /// Where each cell of the ship is relative to `pos`, bow first
+    fn offsets(&self) -> impl Iterator<Item = (isize, isize)> {
+        self.kind.shape().oriented(self.rot, self.mirrored)
+    }
+    /// Returns `true` if part of the ship would be off the board
+    pub fn overflows(&self, geometry: Geometry) -> bool {
+        self.offsets()
+            .any(|(dx, dy)| geometry.offset(self.pos, dx, dy).is_none())
+    }
+    /// Which edge of a flat board the ship sticks out past the furthest, and
+    /// by how many cells
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn overhang(&self) -> Option<(Edge, usize)> {
+        let (x, y) = (self.pos.x() as isize, self.pos.y() as isize);
+        self.offsets()
+            .flat_map(|(dx, dy)| {
+                let (x, y) = (x + dx, y + dy);
+                [
+                    (Edge::Left, -x),
+                    (Edge::Right, x - 9),
+                    (Edge::Top, -y),
+                    (Edge::Bottom, y - 9),
+                ]
+            })
+            .filter(|(_edge, by)| *by > 0)
+            .max_by_key(|(_edge, by)| *by)
+            .map(|(edge, by)| (edge, by.unsigned_abs()))
+    }
+    /// The water around this ship, diagonals included
+    pub fn surrounding(&self, geometry: Geometry) -> Vec<Cell> {
+        let occupies = self.occupies(geometry);
+        let mut out: Vec<Cell> = Vec::with_capacity(2 * self.length() + 6);
+        for cell in &occupies {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let Some(near) = geometry.offset(*cell, dx, dy) else {
+                        continue;
+                    };
+                    if !occupies.contains(&near) && !out.contains(&near) {
+                        out.push(near);
                     }
                 }
-                _ => {}
-            };
-            let (cell_x, cell_y) = match self.rot {
-                ShipRotation::Up => (pos_x, pos_y - i),
-                ShipRotation::Down => (pos_x, pos_y + i),
-                ShipRotation::Left => (pos_x - i, pos_y),
-                ShipRotation::Right => (pos_x + i, pos_y),
-            };
-            occupies.push(Cell::new(cell_x, cell_y));
+            }
         }
-        occupies
+        out
+    }
+    /// The cells this ship is on. Parts that overflow the board are left out.
+    pub fn occupies(&self, geometry: Geometry) -> Vec<Cell> {
+        self.offsets()
+            .filter_map(|(dx, dy)| geometry.offset(self.pos, dx, dy))
+            .collect()
+    }
+    /// If the ship crosses an edge of the board at `cell`, which way it
+    /// continues on the other side
+    pub fn wraps_at(&self, cell: Cell, geometry: Geometry) -> Option<ShipRotation> {
+        let offsets: Vec<(isize, isize)> = self.offsets().collect();
+        let (x, y) = offsets
+            .iter()
+            .find(|(dx, dy)| geometry.offset(self.pos, *dx, *dy) == Some(cell))?;
+        offsets.iter().find_map(|(nx, ny)| {
+            let (dx, dy) = (nx - x, ny - y);
+            if dx.abs() > 1 || dy.abs() > 1 {
+                return None;
+            }
+            // next to each other on the ship, but not on the board
+            let next = geometry.offset(self.pos, *nx, *ny)?;
+            match (
+                cell.x().abs_diff(next.x()) > 1,
+                cell.y().abs_diff(next.y()) > 1,
+            ) {
+                (true, _) if dx > 0 => Some(ShipRotation::Right),
+                (true, _) => Some(ShipRotation::Left),
+                (_, true) if dy > 0 => Some(ShipRotation::Down),
+                (_, true) => Some(ShipRotation::Up),
+                _ => None,
+            }
+        })
     }
 }
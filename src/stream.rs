@@ -1,22 +1,182 @@
+use crate::clock::{TimeControl, Timeout};
+use crate::room::{self, Rooms, ToRoom};
+use crate::rules::{Mode, Rules};
 use crate::Error;
 use owo_colors::OwoColorize;
 use std::fmt::Display;
-use std::time::Duration;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpStream;
+use tokio::select;
+use tokio::sync::{mpsc, oneshot};
 
-pub async fn handle_stream(stream: TcpStream) {
-    if let Err(e) = run(stream).await {
+type Input = Lines<BufReader<OwnedReadHalf>>;
+
+pub async fn handle_stream(stream: TcpStream, rooms: Rooms) {
+    if let Err(e) = run(stream, rooms).await {
         eprintln!("{e:?}");
     }
 }
 
-async fn run(mut stream: TcpStream) -> Result<(), Error> {
-    stream.colorful("test".cyan()).await?;
-    tokio::time::sleep(Duration::from_secs(1)).await;
-    stream.clear().await?;
-    tokio::time::sleep(Duration::from_secs(1)).await;
-    Ok(())
+async fn run(stream: TcpStream, rooms: Rooms) -> Result<(), Error> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    write.clear().await?;
+    write.colorful("Welcome to battleship!\r\n".cyan()).await?;
+    let (out, mut screen) = mpsc::unbounded_channel();
+    let (player, room) = loop {
+        let Some(name) = ask(&mut write, &mut lines, "Room to join or start: ").await? else {
+            return Ok(());
+        };
+        if name.is_empty() {
+            continue;
+        }
+        let listed = rooms
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(&name)
+            .cloned();
+        let room = match listed {
+            Some(room) => room,
+            None => {
+                let Some(rules) = pick_rules(&mut write, &mut lines).await? else {
+                    return Ok(());
+                };
+                open(&rooms, name, rules)
+            }
+        };
+        let (seat, seated) = oneshot::channel();
+        let join = ToRoom::Join {
+            out: out.clone(),
+            seat,
+        };
+        if room.send(join).is_ok() {
+            if let Ok(Some(player)) = seated.await {
+                break (player, room);
+            }
+        }
+        write
+            .colorful("That game already started, try another room.\r\n")
+            .await?;
+    };
+    // the room holds the only sender now, so the screen ends when it does
+    drop(out);
+    let played = async {
+        loop {
+            select! {
+                line = lines.next_line() => match line? {
+                    Some(line) => {
+                        let _ = room.send(ToRoom::Line(player, line.trim().to_string()));
+                    }
+                    None => return Ok(()),
+                },
+                text = screen.recv() => match text {
+                    Some(text) => write.colorful(text.replace('\n', "\r\n")).await?,
+                    None => return Ok(()),
+                },
+            }
+        }
+    }
+    .await;
+    let _ = room.send(ToRoom::Left(player));
+    played
+}
+
+/// Starts a room called `name`, unless someone beat us to it, and returns
+/// whichever room has that name
+fn open(rooms: &Rooms, name: String, rules: Rules) -> mpsc::UnboundedSender<ToRoom> {
+    let mut listed = rooms
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    listed
+        .entry(name.clone())
+        .or_insert_with(|| {
+            let (room, inbox) = mpsc::unbounded_channel();
+            tokio::spawn(room::run(name, rules, rooms.clone(), inbox));
+            room
+        })
+        .clone()
+}
+
+/// Asks whoever is starting a room how it should be played
+async fn pick_rules(write: &mut OwnedWriteHalf, lines: &mut Input) -> Result<Option<Rules>, Error> {
+    let mut rules = Rules::default();
+    let Some(mode) = choose(write, lines, "Players", Mode::Duel, Mode::next).await? else {
+        return Ok(None);
+    };
+    rules.mode = mode;
+    let Some(clock) = choose(
+        write,
+        lines,
+        "Clock",
+        TimeControl::Unlimited,
+        TimeControl::next,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+    rules.clock = clock;
+    if clock != TimeControl::Unlimited {
+        let Some(timeout) = choose(
+            write,
+            lines,
+            "Out of time",
+            Timeout::RandomShot,
+            Timeout::next,
+        )
+        .await?
+        else {
+            return Ok(None);
+        };
+        rules.timeout = timeout;
+    }
+    Ok(Some(rules))
+}
+
+/// Lists every setting `next` steps through from `first`, and asks for one
+async fn choose<T: Copy + PartialEq + Display>(
+    write: &mut OwnedWriteHalf,
+    lines: &mut Input,
+    title: &str,
+    first: T,
+    next: fn(&mut T),
+) -> Result<Option<T>, Error> {
+    let mut options = vec![first];
+    loop {
+        let mut option = options[options.len() - 1];
+        next(&mut option);
+        if option == first {
+            break;
+        }
+        options.push(option);
+    }
+    write.colorful(format!("{title}:\r\n").cyan()).await?;
+    for (i, option) in options.iter().enumerate() {
+        write.colorful(format!("  {}) {option}\r\n", i + 1)).await?;
+    }
+    loop {
+        let Some(picked) = ask(write, lines, "Pick one [1]: ").await? else {
+            return Ok(None);
+        };
+        if picked.is_empty() {
+            return Ok(Some(first));
+        }
+        match picked.parse::<usize>() {
+            Ok(i) if (1..=options.len()).contains(&i) => return Ok(Some(options[i - 1])),
+            _ => write.colorful("Pick one of the numbers.\r\n").await?,
+        }
+    }
+}
+
+/// Prompts for a line, or `None` if they hung up
+async fn ask(
+    write: &mut OwnedWriteHalf,
+    lines: &mut Input,
+    prompt: &str,
+) -> Result<Option<String>, Error> {
+    write.colorful(prompt).await?;
+    Ok(lines.next_line().await?.map(|line| line.trim().to_string()))
 }
 
 pub trait ConnectedTerminal {
@@ -26,7 +186,7 @@ pub trait ConnectedTerminal {
     }
 }
 
-impl ConnectedTerminal for TcpStream {
+impl<T: AsyncWrite + Unpin> ConnectedTerminal for T {
     async fn colorful(&mut self, data: impl Display) -> Result<(), Error> {
         self.write_all(data.to_string().as_bytes()).await?;
         Ok(())
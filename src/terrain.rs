@@ -0,0 +1,184 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{cell::Cell, Error};
+
+/// Which cells of the board are land. Ships can't be placed on land, and
+/// nobody can fire at it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "SavedTerrain", into = "SavedTerrain")
+)]
+pub struct Terrain {
+    /// One bit per cell, `x + 10 * y`
+    land: u128,
+    /// What the map was generated from, if it was
+    seed: Option<u64>,
+}
+
+/// Terrain as it's saved, land as a list of cells since a lot of JSON
+/// readers choke on 128 bit numbers
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedTerrain {
+    land: Vec<Cell>,
+    seed: Option<u64>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Terrain> for SavedTerrain {
+    fn from(terrain: Terrain) -> Self {
+        Self {
+            land: terrain.land_cells(),
+            seed: terrain.seed,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<SavedTerrain> for Terrain {
+    fn from(saved: SavedTerrain) -> Self {
+        let mut terrain = Self {
+            land: 0,
+            seed: saved.seed,
+        };
+        for cell in saved.land {
+            terrain.set_land(cell, true);
+        }
+        terrain
+    }
+}
+
+impl Terrain {
+    /// Nothing but water
+    pub const OPEN: Self = Self {
+        land: 0,
+        seed: None,
+    };
+
+    const PRESETS: &'static [(&'static str, &'static str)] = &[
+        (
+            "Archipelago",
+            "..........\n\
+             .##.......\n\
+             .#.....#..\n\
+             .......##.\n\
+             ..........\n\
+             ...#......\n\
+             ..###.....\n\
+             ...#...##.\n\
+             ........#.\n\
+             ..........",
+        ),
+        (
+            "Reef",
+            "..........\n\
+             ..........\n\
+             ..#.#.#...\n\
+             ..........\n\
+             ...#.#.#..\n\
+             ..........\n\
+             ..#.#.#...\n\
+             ..........\n\
+             ...#.#.#..\n\
+             ..........",
+        ),
+        (
+            "Strait",
+            "..........\n\
+             ..........\n\
+             ..........\n\
+             ####..####\n\
+             ###....###\n\
+             ..........\n\
+             ..........\n\
+             ..........\n\
+             ..........\n\
+             ..........",
+        ),
+    ];
+
+    pub const fn is_land(&self, cell: Cell) -> bool {
+        self.land & Self::bit(cell) != 0
+    }
+    pub fn set_land(&mut self, cell: Cell, land: bool) {
+        if land {
+            self.land |= Self::bit(cell);
+        } else {
+            self.land &= !Self::bit(cell);
+        }
+    }
+    pub const fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+    pub fn land_cells(&self) -> Vec<Cell> {
+        (0..10)
+            .flat_map(|y| (0..10).map(move |x| Cell::new(x, y)))
+            .filter(|cell| self.is_land(*cell))
+            .collect()
+    }
+    const fn bit(cell: Cell) -> u128 {
+        1 << (cell.x() + 10 * cell.y())
+    }
+    /// Reads a hand-drawn map: ten lines of ten cells, `#` for land and
+    /// `.` for water
+    pub fn from_map(map: &str) -> Result<Self, Error> {
+        let mut out = Self::OPEN;
+        let lines: Vec<&str> = map.lines().map(str::trim).collect();
+        if lines.len() != 10 {
+            return Err(Error::InvalidMap(format!(
+                "expected 10 lines, found {}",
+                lines.len()
+            )));
+        }
+        for (y, line) in lines.iter().enumerate() {
+            if line.chars().count() != 10 {
+                return Err(Error::InvalidMap(format!(
+                    "line {} should be 10 cells long",
+                    y + 1
+                )));
+            }
+            for (x, ch) in line.chars().enumerate() {
+                match ch {
+                    '#' => out.set_land(Cell::new(x, y), true),
+                    '.' => {}
+                    _ => {
+                        return Err(Error::InvalidMap(format!(
+                            "unexpected {ch:?} on line {}",
+                            y + 1
+                        )))
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+    /// The built-in maps, by name
+    pub fn presets() -> Vec<(&'static str, Self)> {
+        Self::PRESETS
+            .iter()
+            .filter_map(|(name, map)| Some((*name, Self::from_map(map).ok()?)))
+            .collect()
+    }
+    /// A handful of small islands, always the same for the same seed
+    pub fn generate(seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut out = Self {
+            land: 0,
+            seed: Some(seed),
+        };
+        for _ in 0..rng.gen_range(3..=5) {
+            let mut cell = Cell::new(rng.gen_range(0..10), rng.gen_range(0..10));
+            for _ in 0..rng.gen_range(1..=3) {
+                out.set_land(cell, true);
+                // islands grow in random directions, but never wrap around
+                let x = cell.x().saturating_add_signed(rng.gen_range(-1..=1)).min(9);
+                let y = cell.y().saturating_add_signed(rng.gen_range(-1..=1)).min(9);
+                cell = Cell::new(x, y);
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,705 @@
+//! `battleship tournament`: plays strategies against each other without a
+//! screen, on every core, and sums up how they did.
+//!
+//! ```text
+//! battleship tournament <strategy> <strategy> [--games N] [--seed N] [--format text|csv|json]
+//! battleship tournament <strategy>... --layouts [--games N] [--seed N] [--format text|csv|json]
+//! ```
+//!
+//! Strategies are `easy`, `normal`, `hard`, or `--bot <command>` for a bot
+//! program, which gets `--time-limit <ms>` to answer each time.
+//!
+//! Head to head, the two take turns going first. With `--layouts`, every
+//! strategy fires at the same set of fleets on its own instead, and only the
+//! shots it took count. Every game is worked out from the seed and its own
+//! number, so the same seed always gives the same results, however many
+//! threads there are.
+
+use std::{
+    any::Any,
+    fmt::Write,
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    ai::{Opponent, Strategy},
+    board::Board,
+    error::Error,
+    event::Action,
+    game::Game,
+    rules::Rules,
+    ship::ShipSetBuilder,
+};
+
+/// Games that go on this many turns are called a draw
+const MAX_TURNS: usize = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(Error::Usage(format!(
+                "{text} isn't a format, try text, csv or json"
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Tournament {
+    pub contenders: Vec<Opponent>,
+    pub rules: Rules,
+    pub games: usize,
+    pub seed: u64,
+    /// Everyone shoots at the same fleets alone, instead of at each other
+    pub layouts: bool,
+}
+
+/// How one game went for one contender
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct Played {
+    won: bool,
+    /// Shots it fired, if it won
+    shots: Option<usize>,
+}
+
+impl Tournament {
+    /// Reads the arguments after `tournament`
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<(Self, Format), Error> {
+        let mut out = Self {
+            contenders: Vec::new(),
+            rules: Rules::default(),
+            games: 1000,
+            seed: 0,
+            layouts: false,
+        };
+        let mut format = Format::Text;
+        let mut limit = Opponent::TIME_LIMIT;
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| Error::Usage(format!("{name} needs a value")))
+            };
+            match arg.as_str() {
+                "--games" => out.games = value("--games")?.parse()?,
+                "--seed" => out.seed = value("--seed")?.parse()?,
+                "--format" => format = value("--format")?.parse()?,
+                "--layouts" => out.layouts = true,
+                "--time-limit" => limit = Duration::from_millis(value("--time-limit")?.parse()?),
+                "--bot" => out.contenders.push(Opponent::External {
+                    command: value("--bot")?,
+                    limit,
+                }),
+                name => out
+                    .contenders
+                    .push(Opponent::Builtin(name.parse().map_err(Error::Usage)?)),
+            }
+        }
+        // the limit goes for every bot, wherever it was given
+        for contender in &mut out.contenders {
+            if let Opponent::External { limit: theirs, .. } = contender {
+                *theirs = limit;
+            }
+        }
+        match out.contenders.len() {
+            0 => Err(Error::Usage("name at least one strategy".to_string())),
+            1 if !out.layouts => Err(Error::Usage(
+                "name two strategies, or use --layouts".to_string(),
+            )),
+            3.. if !out.layouts => Err(Error::Usage(
+                "only two strategies can play head to head, use --layouts for more".to_string(),
+            )),
+            _ => Ok((out, format)),
+        }
+    }
+    /// Makes sure every bot at least starts, before playing any games
+    pub fn check(&self) -> Result<(), Error> {
+        for contender in &self.contenders {
+            contender.strategy(self.seed, true)?;
+        }
+        Ok(())
+    }
+    /// Plays every game, and how each contender did, in order. Fails with
+    /// the first game that crashed, which plays out the same way again from
+    /// the same seed.
+    pub fn run(&self) -> Result<Vec<Stats>, Error> {
+        let threads = thread::available_parallelism().map_or(1, usize::from);
+        let mut results: Vec<(usize, Vec<Played>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|worker| {
+                    scope.spawn(move || {
+                        (worker..self.games)
+                            .step_by(threads)
+                            .map(|game| {
+                                panic::catch_unwind(AssertUnwindSafe(|| self.play(game)))
+                                    .map(|played| (game, played))
+                                    .map_err(|panic| (game, panic_message(&*panic)))
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                })
+                .collect();
+            let mut results = Vec::with_capacity(self.games);
+            let mut crashed = Vec::new();
+            for worker in workers {
+                match worker
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+                {
+                    Ok(played) => results.extend(played),
+                    Err(crash) => crashed.push(crash),
+                }
+            }
+            // the earliest, whichever worker got there first
+            match crashed.into_iter().min() {
+                Some((game, message)) => Err(Error::GameCrashed { game, message }),
+                None => Ok(results),
+            }
+        })?;
+        results.sort_unstable_by_key(|(game, _played)| *game);
+        let names = self.names();
+        Ok((0..self.contenders.len())
+            .map(|index| {
+                let played: Vec<Played> = results
+                    .iter()
+                    .map(|(_game, played)| played[index])
+                    .collect();
+                Stats::new(names[index].clone(), &played)
+            })
+            .collect())
+    }
+    /// Contender names, numbered when the same one's in twice
+    fn names(&self) -> Vec<String> {
+        self.contenders
+            .iter()
+            .enumerate()
+            .map(|(index, contender)| {
+                let before = self.contenders[..index]
+                    .iter()
+                    .filter(|other| *other == contender)
+                    .count();
+                if before == 0 {
+                    contender.to_string()
+                } else {
+                    format!("{contender} ({})", before + 1)
+                }
+            })
+            .collect()
+    }
+    /// Plays the `game`th game, everything random about it coming from the
+    /// seed and `game`
+    fn play(&self, game: usize) -> Vec<Played> {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(game as u64);
+        if self.layouts {
+            let mut fleet = ShipSetBuilder::new(&self.rules);
+            let layout = fleet
+                .randomize(rng.gen())
+                .then(|| fleet.build().ok())
+                .flatten();
+            self.contenders
+                .iter()
+                .map(|contender| {
+                    // a bot that won't start just loses
+                    let strategy = contender.strategy(rng.gen(), true).ok();
+                    let shots = layout
+                        .clone()
+                        .zip(strategy)
+                        .and_then(|(layout, strategy)| solo(strategy, Board::new(layout)));
+                    Played {
+                        won: shots.is_some(),
+                        shots,
+                    }
+                })
+                .collect()
+        } else {
+            // take turns going first
+            let seats = if game.is_multiple_of(2) {
+                [0, 1]
+            } else {
+                [1, 0]
+            };
+            let strategies = seats.map(|seat| self.contenders[seat].strategy(rng.gen(), true).ok());
+            let outcome = duel(&self.rules, strategies);
+            let mut out = vec![
+                Played {
+                    won: false,
+                    shots: None,
+                };
+                2
+            ];
+            if let Some((winner, shots)) = outcome {
+                out[seats[winner]] = Played { won: true, shots };
+            }
+            out
+        }
+    }
+}
+
+/// Whatever a panic said, if it said anything
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "no message".to_string())
+}
+
+/// Fires at `board` until it's lost, and how many shots that took. `None`
+/// if the strategy gave up first.
+fn solo(mut strategy: Box<dyn Strategy>, mut board: Board) -> Option<usize> {
+    let mut shots = 0;
+    while !board.lost() {
+        let [cell] = strategy.aim(0, &board, 1)[..] else {
+            return None;
+        };
+        board.fire(&cell)?;
+        shots += 1;
+    }
+    Some(shots)
+}
+
+/// Plays a whole game between two strategies. Returns which one won and the
+/// shots it fired, or `None` for a draw. One that didn't start, or couldn't
+/// place its fleet, loses straight away. Shots are `None` if the other one
+/// forfeited.
+fn duel(
+    rules: &Rules,
+    strategies: [Option<Box<dyn Strategy>>; 2],
+) -> Option<(usize, Option<usize>)> {
+    let mut boards = Vec::with_capacity(2);
+    let mut ready = Vec::with_capacity(2);
+    for (player, strategy) in strategies.into_iter().enumerate() {
+        let Some((fleet, strategy)) = strategy.and_then(|mut strategy| {
+            let fleet = strategy.place(rules)?;
+            Some((fleet, strategy))
+        }) else {
+            return Some((1 - player, None));
+        };
+        boards.push(Board::new(fleet));
+        ready.push(strategy);
+    }
+    let mut strategies = ready;
+    let mut game = Game::new(rules, boards);
+    let mut fired = [0; 2];
+    let mut forfeited = false;
+    for _ in 0..MAX_TURNS {
+        if let Some(winners) = game.winners() {
+            let winner = *winners.first()?;
+            return Some((winner, (!forfeited).then_some(fired[winner])));
+        }
+        let player = game.current();
+        let defender = 1 - player;
+        let shots = rules
+            .salvo
+            .shots(game.board(player))
+            .min(game.board(defender).unfired())
+            .max(1);
+        let cells = strategies[player].aim(defender, game.board(defender), shots);
+        fired[player] += cells.len();
+        if game.act(defender, Action::Volley(cells)).is_none() {
+            game.resign(player);
+            forfeited = true;
+        }
+    }
+    None
+}
+
+/// How one contender did over the whole tournament
+#[derive(Clone, PartialEq, Debug)]
+pub struct Stats {
+    pub name: String,
+    pub games: usize,
+    pub wins: usize,
+    /// 95% confidence interval of the win rate
+    pub win_rate: Interval,
+    /// 95% confidence interval of the mean shots it took to win
+    pub mean_shots: Interval,
+    pub median_shots: f64,
+    pub p10_shots: f64,
+    pub p90_shots: f64,
+}
+
+/// An estimate and how far off it might be
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Interval {
+    pub value: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+/// Standard normal quantile for 95% confidence
+const Z: f64 = 1.96;
+
+impl Stats {
+    #[allow(clippy::cast_precision_loss)]
+    fn new(name: String, played: &[Played]) -> Self {
+        let games = played.len();
+        let wins = played.iter().filter(|played| played.won).count();
+        let mut shots: Vec<f64> = played
+            .iter()
+            .filter_map(|played| played.shots)
+            .map(|shots| shots as f64)
+            .collect();
+        shots.sort_unstable_by(f64::total_cmp);
+        Self {
+            name,
+            games,
+            wins,
+            win_rate: wilson(wins, games),
+            mean_shots: mean(&shots),
+            median_shots: percentile(&shots, 50.0),
+            p10_shots: percentile(&shots, 10.0),
+            p90_shots: percentile(&shots, 90.0),
+        }
+    }
+}
+
+/// Wilson score interval, which behaves near 0% and 100% unlike the usual one
+#[allow(clippy::cast_precision_loss)]
+fn wilson(wins: usize, games: usize) -> Interval {
+    if games == 0 {
+        return Interval {
+            value: f64::NAN,
+            low: f64::NAN,
+            high: f64::NAN,
+        };
+    }
+    let n = games as f64;
+    let p = wins as f64 / n;
+    let z2 = Z * Z;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let spread = Z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    Interval {
+        value: p,
+        low: centre - spread,
+        high: centre + spread,
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn mean(samples: &[f64]) -> Interval {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let spread = if samples.len() > 1 {
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        Z * (variance / n).sqrt()
+    } else {
+        f64::NAN
+    };
+    Interval {
+        value: mean,
+        low: mean - spread,
+        high: mean + spread,
+    }
+}
+
+/// Nearest rank percentile of `sorted`
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+const COLUMNS: [&str; 13] = [
+    "strategy",
+    "games",
+    "wins",
+    "win_rate",
+    "win_rate_low",
+    "win_rate_high",
+    "mean_shots",
+    "mean_shots_low",
+    "mean_shots_high",
+    "median_shots",
+    "p10_shots",
+    "p90_shots",
+    "seed",
+];
+
+/// The numbers of a row of [`COLUMNS`], after the name
+fn row(stats: &Stats, seed: u64) -> [String; 12] {
+    let num = |x: f64| {
+        if x.is_finite() {
+            format!("{x:.4}")
+        } else {
+            String::new()
+        }
+    };
+    [
+        stats.games.to_string(),
+        stats.wins.to_string(),
+        num(stats.win_rate.value),
+        num(stats.win_rate.low),
+        num(stats.win_rate.high),
+        num(stats.mean_shots.value),
+        num(stats.mean_shots.low),
+        num(stats.mean_shots.high),
+        num(stats.median_shots),
+        num(stats.p10_shots),
+        num(stats.p90_shots),
+        seed.to_string(),
+    ]
+}
+
+pub fn to_csv(stats: &[Stats], seed: u64) -> String {
+    let mut out = COLUMNS.join(",");
+    out.push('\n');
+    for stats in stats {
+        // bot commands can have anything in them
+        if stats.name.contains([',', '"', '\n']) {
+            let _ = write!(out, "\"{}\"", stats.name.replace('"', "\"\""));
+        } else {
+            out.push_str(&stats.name);
+        }
+        for value in row(stats, seed) {
+            out.push(',');
+            out.push_str(&value);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub fn to_json(stats: &[Stats], seed: u64) -> String {
+    let objects: Vec<String> = stats
+        .iter()
+        .map(|stats| {
+            let fields: Vec<String> = std::iter::once(json_string(&stats.name))
+                .chain(row(stats, seed).map(|value| {
+                    // JSON has no NaN
+                    if value.is_empty() {
+                        "null".to_string()
+                    } else {
+                        value
+                    }
+                }))
+                .zip(COLUMNS)
+                .map(|(value, column)| format!("\"{column}\": {value}"))
+                .collect();
+            format!("  {{{}}}", fields.join(", "))
+        })
+        .collect();
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+/// `text` quoted for JSON, which wants control characters escaped too
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn to_text(stats: &[Stats], seed: u64) -> String {
+    let mut out = format!("seed {seed}\n");
+    for stats in stats {
+        let _ = write!(
+            out,
+            "{: <12} won {}/{} ({:.1}%, {:.1}-{:.1}%)",
+            stats.name,
+            stats.wins,
+            stats.games,
+            stats.win_rate.value * 100.0,
+            stats.win_rate.low * 100.0,
+            stats.win_rate.high * 100.0,
+        );
+        // wins by forfeit don't count towards shots
+        if stats.mean_shots.value.is_finite() {
+            let _ = write!(
+                out,
+                ", shots to win: mean {:.2} ({:.2}-{:.2}), median {}, p10 {}, p90 {}",
+                stats.mean_shots.value,
+                stats.mean_shots.low,
+                stats.mean_shots.high,
+                stats.median_shots,
+                stats.p10_shots,
+                stats.p90_shots,
+            );
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Difficulty;
+
+    fn args(text: &str) -> Result<(Tournament, Format), Error> {
+        Tournament::from_args(text.split_whitespace().map(ToString::to_string))
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn wilson_stays_inside_at_the_ends() {
+        let none = wilson(0, 20);
+        assert!(close(none.value, 0.0) && close(none.low, 0.0));
+        assert!(none.high > 0.0 && none.high < 0.2);
+        let all = wilson(20, 20);
+        assert!(close(all.value, 1.0) && close(all.high, 1.0));
+        assert!(all.low < 1.0 && all.low > 0.8);
+        let half = wilson(5, 10);
+        assert!(close(half.low, 0.2366) && close(half.high, 0.7634));
+        assert!(wilson(0, 0).value.is_nan());
+    }
+
+    #[test]
+    fn mean_and_its_spread() {
+        let five = mean(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert!(close(five.value, 3.0));
+        // sample variance 2.5, so 1.96 * sqrt(2.5 / 5) either side
+        assert!(close(five.low, 1.6141) && close(five.high, 4.3859));
+        let one = mean(&[7.0]);
+        assert!(close(one.value, 7.0) && one.low.is_nan());
+        assert!(mean(&[]).value.is_nan());
+    }
+
+    #[test]
+    fn percentile_is_nearest_rank() {
+        let sorted: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert!(close(percentile(&sorted, 10.0), 1.0));
+        assert!(close(percentile(&sorted, 50.0), 5.0));
+        assert!(close(percentile(&sorted, 55.0), 6.0));
+        assert!(close(percentile(&sorted, 90.0), 9.0));
+        assert!(close(percentile(&sorted, 100.0), 10.0));
+        assert!(close(percentile(&sorted, 0.0), 1.0));
+        assert!(close(percentile(&[4.0], 90.0), 4.0));
+        assert!(percentile(&[], 50.0).is_nan());
+    }
+
+    #[test]
+    fn no_wins_leaves_shots_blank() {
+        let lost = Played {
+            won: false,
+            shots: None,
+        };
+        let stats = [Stats::new("easy".to_string(), &[lost, lost])];
+        let csv = to_csv(&stats, 1);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("easy,2,0,0.0000,0.0000,0.6576,,,,,,,1")
+        );
+        let json = to_json(&stats, 1);
+        assert!(json.contains("\"mean_shots\": null"));
+        assert!(json.contains("\"p90_shots\": null"));
+        assert!(json.contains("\"win_rate\": 0.0000"));
+        assert!(!to_text(&stats, 1).contains("shots to win"));
+    }
+
+    #[test]
+    fn json_escapes_names() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(
+            json_string("a \"b\" \\ c\n\t\u{1}"),
+            "\"a \\\"b\\\" \\\\ c\\n\\t\\u0001\""
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_parses() {
+        let won = Played {
+            won: true,
+            shots: Some(40),
+        };
+        let stats = [Stats::new("./bot --x \"y\"\u{7}\n".to_string(), &[won])];
+        let parsed: serde_json::Value = serde_json::from_str(&to_json(&stats, 9)).unwrap();
+        assert_eq!(parsed[0]["strategy"], "./bot --x \"y\"\u{7}\n");
+        assert_eq!(parsed[0]["mean_shots_low"], serde_json::Value::Null);
+        assert_eq!(parsed[0]["seed"], 9);
+    }
+
+    #[test]
+    fn reads_arguments() {
+        let (tournament, format) = args("easy --games 50 hard --seed 7 --format csv").unwrap();
+        assert_eq!(
+            tournament.contenders,
+            [
+                Opponent::Builtin(Difficulty::Easy),
+                Opponent::Builtin(Difficulty::Hard)
+            ]
+        );
+        assert_eq!((tournament.games, tournament.seed), (50, 7));
+        assert!(!tournament.layouts);
+        assert_eq!(format, Format::Csv);
+        // the time limit goes for bots named before it too
+        let (tournament, format) = args("--bot ./a --layouts --time-limit 250").unwrap();
+        assert_eq!(
+            tournament.contenders,
+            [Opponent::External {
+                command: "./a".to_string(),
+                limit: Duration::from_millis(250),
+            }]
+        );
+        assert!(tournament.layouts);
+        assert_eq!(format, Format::Text);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for bad in [
+            "",
+            "easy",
+            "easy normal hard",
+            "easy normal --games",
+            "easy normal --games lots",
+            "easy normal --format xml",
+            "easy medium",
+        ] {
+            assert!(args(bad).is_err(), "{bad:?} was let through");
+        }
+    }
+
+    #[test]
+    fn same_seed_same_results() {
+        let (tournament, _format) = args("easy normal --games 40 --seed 11").unwrap();
+        let first = tournament.run().unwrap();
+        let again = tournament.run().unwrap();
+        assert_eq!(to_csv(&first, 11), to_csv(&again, 11));
+        assert_eq!(first.iter().map(|stats| stats.games).sum::<usize>(), 80);
+        let (other, _format) = args("easy normal --games 40 --seed 12").unwrap();
+        assert_ne!(to_csv(&first, 11), to_csv(&other.run().unwrap(), 11));
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{Stdout, Write};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::KeyCode,
+    queue,
+    style::{Print, PrintStyledContent, Stylize},
+    terminal::Clear,
+};
+
+use crate::{
+    ai::{Opponent, Strategy},
+    board::Board,
+    cell::Cell,
+    game::Game,
+    rules::Rules,
+    ship::ShipSetBuilder,
+    Error,
+};
+
+use super::{
+    exit,
+    play::{computer_turn, turn},
+    setup::do_place,
+    show_pass, wait_on_player,
+};
+
+/// A pass 'n play game, from placing fleets to the last one afloat
+pub fn local_game(stdout: &mut Stdout, rules: &Rules) -> Result<(), Error> {
+    let mut cursor = Cell::new(0, 0);
+    let mut boards = Vec::with_capacity(rules.mode.players());
+    for player in 1..=rules.mode.players() {
+        show_pass(stdout, player)?;
+        boards.push(do_place(
+            stdout,
+            &mut cursor,
+            player,
+            "Place your ships",
+            rules,
+        )?);
+    }
+    let mut game = Game::new(rules, boards);
+    let winners = loop {
+        if let Some(winners) = game.winners() {
+            break winners;
+        }
+        let player = game.current();
+        show_pass(stdout, player + 1)?;
+        let defender = match game.opponents()[..] {
+            [only] => only,
+            _ => pick_opponent(stdout, &game)?,
+        };
+        turn(stdout, &mut game, defender, &mut cursor, false)?;
+    };
+    show_winners(stdout, &winners)
+}
+
+/// Player 1 against the computer, which plays everyone else
+pub fn computer_game(stdout: &mut Stdout, rules: &Rules, opponent: &Opponent) -> Result<(), Error> {
+    let mut cursor = Cell::new(0, 0);
+    let mut computers: Vec<Box<dyn Strategy>> = (1..rules.mode.players())
+        .map(|_| opponent.strategy(rand::random(), false))
+        .collect::<Result<_, _>>()?;
+    let mut boards = Vec::with_capacity(rules.mode.players());
+    boards.push(do_place(stdout, &mut cursor, 1, "Place your ships", rules)?);
+    let mut forfeits = Vec::new();
+    for (player, computer) in (1..).zip(&mut computers) {
+        let ships = match computer.place(rules) {
+            Some(ships) => ships,
+            // a bot that sent a bad fleet gets any old one, and forfeits
+            None if computer.trouble().is_some() => {
+                forfeits.push(player);
+                let mut builder = ShipSetBuilder::new(rules);
+                builder.randomize(rand::random());
+                builder.build()?
+            }
+            // an empty builder fails with whatever it was that didn't fit
+            None => ShipSetBuilder::new(rules).build()?,
+        };
+        boards.push(Board::new(ships));
+    }
+    let mut game = Game::new(rules, boards);
+    for player in forfeits {
+        game.resign(player);
+        let trouble = computers[player - 1].trouble().unwrap_or_default();
+        queue!(
+            stdout,
+            Clear(crossterm::terminal::ClearType::All),
+            MoveTo(2, 2),
+            Print(format!("Player {} forfeits: {trouble}", player + 1))
+        )?;
+        stdout.flush()?;
+        wait_on_player()?;
+    }
+    queue!(stdout, Clear(crossterm::terminal::ClearType::All))?;
+    let winners = loop {
+        if let Some(winners) = game.winners() {
+            break winners;
+        }
+        let player = game.current();
+        if player > 0 {
+            computer_turn(stdout, &mut game, computers[player - 1].as_mut(), 0)?;
+            continue;
+        }
+        let defender = match game.opponents()[..] {
+            [only] => only,
+            _ => pick_opponent(stdout, &game)?,
+        };
+        queue!(stdout, Clear(crossterm::terminal::ClearType::All))?;
+        // bots in other programs can't be told to forget shots, so only
+        // games against the built in ones are for practice
+        let practice = matches!(opponent, Opponent::Builtin(_));
+        if !turn(stdout, &mut game, defender, &mut cursor, practice)? {
+            if let Some(before) = undo(&game) {
+                game = before;
+            }
+        }
+        queue!(stdout, Clear(crossterm::terminal::ClearType::All))?;
+    };
+    show_winners(stdout, &winners)
+}
+
+/// The game as it was before player 1's last turn, computers' turns since
+/// and all, or `None` if they haven't had one
+fn undo(game: &Game) -> Option<Game> {
+    let mut out = game.rewound()?.ok()?;
+    while out.current() != 0 {
+        out = out.rewound()?.ok()?;
+    }
+    Some(out)
+}
+
+fn show_winners(stdout: &mut Stdout, winners: &[usize]) -> Result<(), Error> {
+    queue!(
+        stdout,
+        Clear(crossterm::terminal::ClearType::All),
+        MoveTo(2, 2),
+        Print(match winners {
+            [winner] => format!("Player {} wins!", winner + 1),
+            _ => {
+                let names: Vec<String> = winners.iter().map(|p| (p + 1).to_string()).collect();
+                format!("Players {} win!", names.join(" and "))
+            }
+        })
+    )?;
+    stdout.flush()?;
+    wait_on_player()?;
+    Ok(())
+}
+
+/// Lets the current player choose whose board to fire at
+fn pick_opponent(stdout: &mut Stdout, game: &Game) -> Result<usize, Error> {
+    let opponents = game.opponents();
+    let (term_width, term_height) = crossterm::terminal::size()?;
+    queue!(stdout, Clear(crossterm::terminal::ClearType::All), Hide)?;
+    let mut selected = 0;
+    #[allow(clippy::cast_possible_truncation)]
+    let top = (term_height / 2).saturating_sub(opponents.len() as u16 / 2);
+    queue!(
+        stdout,
+        MoveTo(term_width / 2 - 20, top.saturating_sub(2)),
+        Print(format!("Player {}, pick a target:", game.current() + 1))
+    )?;
+    loop {
+        for (i, opponent) in opponents.iter().enumerate() {
+            let afloat = game.board(*opponent).afloat();
+            let line = format!(
+                " Player {}: {afloat: >2} ships afloat{: <14}",
+                opponent + 1,
+                ""
+            );
+            let line = if i == selected {
+                line.on_dark_blue().grey()
+            } else {
+                line.on_grey().dark_blue()
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            queue!(
+                stdout,
+                MoveTo(term_width / 2 - 20, top + i as u16),
+                PrintStyledContent(line)
+            )?;
+        }
+        stdout.flush()?;
+        match crate::util::next_key()?.code {
+            KeyCode::Up => selected = selected.checked_sub(1).unwrap_or(opponents.len() - 1),
+            KeyCode::Down => selected = (selected + 1) % opponents.len(),
+            KeyCode::Char(' ') | KeyCode::Enter => break,
+            KeyCode::Esc => exit(),
+            _ => {}
+        }
+    }
+    queue!(stdout, Clear(crossterm::terminal::ClearType::All), Show)?;
+    Ok(opponents[selected])
+}
@@ -12,18 +12,23 @@ use crossterm::{
     terminal::Clear,
 };
 
-use crate::Error;
+use crate::{
+    ai::{Difficulty, Opponent},
+    Error,
+};
 
 use super::exit;
 
 pub enum PlayMode {
     Local,
+    Computer(Opponent),
     Join(SocketAddr),
     Host(u16),
 }
 
 pub enum DatalessPlayMode {
     Local,
+    Computer,
     Join,
     Host,
 }
@@ -35,6 +40,10 @@ pub fn select_play_mode(stdout: &mut Stdout) -> Result<PlayMode, Error> {
     let mut in_progress;
     match play_mode {
         DatalessPlayMode::Local => return Ok(PlayMode::Local),
+        DatalessPlayMode::Computer => match pick_difficulty(stdout, width, height)? {
+            Some(difficulty) => return Ok(PlayMode::Computer(Opponent::Builtin(difficulty))),
+            None => in_progress = "python3 bot.py".to_string(),
+        },
         DatalessPlayMode::Join => in_progress = "127.0.0.1:9416".to_string(),
         DatalessPlayMode::Host => in_progress = "9416".to_string(),
     }
@@ -52,6 +61,11 @@ pub fn select_play_mode(stdout: &mut Stdout) -> Result<PlayMode, Error> {
         )?;
         let parse_error = match play_mode {
             DatalessPlayMode::Local => break,
+            DatalessPlayMode::Computer => in_progress
+                .split_whitespace()
+                .next()
+                .is_none()
+                .then(|| "Type the command that starts your bot".to_string()),
             DatalessPlayMode::Join => in_progress
                 .parse::<SocketAddr>()
                 .err()
@@ -88,6 +102,10 @@ pub fn select_play_mode(stdout: &mut Stdout) -> Result<PlayMode, Error> {
     }
     let final_mode = match play_mode {
         DatalessPlayMode::Local => PlayMode::Local,
+        DatalessPlayMode::Computer => PlayMode::Computer(Opponent::External {
+            command: in_progress,
+            limit: Opponent::TIME_LIMIT,
+        }),
         DatalessPlayMode::Join => PlayMode::Join(std::net::SocketAddr::from_str(&in_progress)?),
         DatalessPlayMode::Host => PlayMode::Host(in_progress.parse()?),
     };
@@ -107,6 +125,11 @@ fn pick_mode(
         } else {
             "   Pass 'n Play    ".on_grey().dark_blue()
         };
+        let computer = if matches!(play_mode, DatalessPlayMode::Computer) {
+            " Play the computer ".on_dark_blue().grey()
+        } else {
+            " Play the computer ".on_grey().dark_blue()
+        };
         let join = if matches!(play_mode, DatalessPlayMode::Join) {
             " Join network game ".on_dark_blue().grey()
         } else {
@@ -122,8 +145,10 @@ fn pick_mode(
             MoveTo(term_width / 2 - 10, term_height / 2 - 1),
             PrintStyledContent(pass_n_play),
             MoveTo(term_width / 2 - 10, term_height / 2),
-            PrintStyledContent(join),
+            PrintStyledContent(computer),
             MoveTo(term_width / 2 - 10, term_height / 2 + 1),
+            PrintStyledContent(join),
+            MoveTo(term_width / 2 - 10, term_height / 2 + 2),
             PrintStyledContent(host),
         )?;
         stdout.flush()?;
@@ -131,13 +156,15 @@ fn pick_mode(
             KeyCode::Up => {
                 play_mode = match play_mode {
                     DatalessPlayMode::Local => DatalessPlayMode::Host,
-                    DatalessPlayMode::Join => DatalessPlayMode::Local,
+                    DatalessPlayMode::Computer => DatalessPlayMode::Local,
+                    DatalessPlayMode::Join => DatalessPlayMode::Computer,
                     DatalessPlayMode::Host => DatalessPlayMode::Join,
                 }
             }
             KeyCode::Down => {
                 play_mode = match play_mode {
-                    DatalessPlayMode::Local => DatalessPlayMode::Join,
+                    DatalessPlayMode::Local => DatalessPlayMode::Computer,
+                    DatalessPlayMode::Computer => DatalessPlayMode::Join,
                     DatalessPlayMode::Join => DatalessPlayMode::Host,
                     DatalessPlayMode::Host => DatalessPlayMode::Local,
                 }
@@ -150,3 +177,41 @@ fn pick_mode(
     queue!(stdout, Show)?;
     Ok(play_mode)
 }
+
+/// A difficulty, or `None` for a bot program
+fn pick_difficulty(
+    stdout: &mut Stdout,
+    term_width: u16,
+    term_height: u16,
+) -> Result<Option<Difficulty>, Error> {
+    queue!(stdout, Clear(crossterm::terminal::ClearType::All), Hide)?;
+    let choices = [
+        Some(Difficulty::Easy),
+        Some(Difficulty::Normal),
+        Some(Difficulty::Hard),
+        None,
+    ];
+    let mut selected = 1;
+    loop {
+        let label = choices[selected].map_or_else(|| "Bot program".to_string(), |d| d.to_string());
+        queue!(
+            stdout,
+            MoveTo(term_width / 2 - 10, term_height / 2 - 1),
+            PrintStyledContent("    Difficulty     ".grey()),
+            MoveTo(term_width / 2 - 10, term_height / 2),
+            PrintStyledContent(format!("< {label: ^15} >").on_dark_blue().grey()),
+        )?;
+        stdout.flush()?;
+        match crate::util::next_key()?.code {
+            KeyCode::Left | KeyCode::Up => {
+                selected = selected.checked_sub(1).unwrap_or(choices.len() - 1);
+            }
+            KeyCode::Right | KeyCode::Down => selected = (selected + 1) % choices.len(),
+            KeyCode::Char(' ') | KeyCode::Enter => break,
+            KeyCode::Esc => exit(),
+            _ => {}
+        };
+    }
+    queue!(stdout, Clear(crossterm::terminal::ClearType::All), Show)?;
+    Ok(choices[selected])
+}
@@ -1,9 +1,17 @@
+pub mod game;
 pub mod menu;
 pub mod play;
+pub mod rules;
 pub mod setup;
 
-use crate::error::Error;
+use crate::{
+    cell::Cell,
+    error::Error,
+    rules::{Geometry, Rules},
+    ship::{ShipRotation, ShipState},
+};
 
+use menu::PlayMode;
 
 use crossterm::{
     cursor::{MoveTo, Show},
@@ -14,6 +22,34 @@ use crossterm::{
 };
 use std::io::{Stdout, Write};
 
+/// `battleship play`: the main menu, then whatever game was picked from it
+pub fn play() -> Result<(), Error> {
+    crossterm::terminal::enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    let mut rules = Rules::default();
+    match menu::select_play_mode(&mut stdout)? {
+        PlayMode::Local => {
+            rules::edit_rules(&mut stdout, &mut rules)?;
+            game::local_game(&mut stdout, &rules)?;
+        }
+        PlayMode::Computer(opponent) => {
+            rules::edit_rules(&mut stdout, &mut rules)?;
+            game::computer_game(&mut stdout, &rules, &opponent)?;
+        }
+        PlayMode::Join(_) | PlayMode::Host(_) => {
+            queue!(
+                stdout,
+                Clear(crossterm::terminal::ClearType::All),
+                MoveTo(2, 2),
+                Print("That isn't ready yet, sorry!")
+            )?;
+            stdout.flush()?;
+            wait_on_player()?;
+        }
+    }
+    exit()
+}
+
 pub fn show_pass(stdout: &mut Stdout, player: usize) -> Result<(), Error> {
     queue!(
         stdout,
@@ -68,6 +104,51 @@ pub fn clear_msgs(out: &mut Stdout) -> Result<(), Error> {
     Ok(())
 }
 
+/// Asks for a line of text below the board. `None` if they backed out with Esc.
+pub fn prompt(stdout: &mut Stdout, question: &str) -> Result<Option<String>, Error> {
+    let mut in_progress = String::new();
+    loop {
+        queue!(
+            stdout,
+            MoveTo(0, 14),
+            Clear(crossterm::terminal::ClearType::CurrentLine),
+            Print(question),
+            Print(&in_progress)
+        )?;
+        stdout.flush()?;
+        let key = crate::util::next_key()?;
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            exit();
+        }
+        match key.code {
+            KeyCode::Char(ch) => in_progress.push(ch),
+            KeyCode::Backspace => {
+                in_progress.pop();
+            }
+            KeyCode::Esc => break Ok(None),
+            KeyCode::Enter => break Ok(Some(in_progress)),
+            _ => {}
+        }
+    }
+}
+
+/// What to draw on an unshot cell of `ship`, so that ships lying across an
+/// edge of the board or diagonally still read as one ship
+pub fn ship_glyph(ship: Option<ShipState>, cell: Cell, geometry: Geometry) -> &'static str {
+    let Some(ship) = ship else {
+        return "  ";
+    };
+    match (ship.wraps_at(cell, geometry), ship.rotation()) {
+        (Some(ShipRotation::Up), _) => "^^",
+        (Some(ShipRotation::Down), _) => "vv",
+        (Some(ShipRotation::Left), _) => "<<",
+        (Some(ShipRotation::Right), _) => ">>",
+        (_, ShipRotation::UpLeft | ShipRotation::DownRight) => "\\\\",
+        (_, ShipRotation::UpRight | ShipRotation::DownLeft) => "//",
+        _ => "  ",
+    }
+}
+
 #[allow(clippy::missing_const_for_fn)]
 pub fn debounce_sleep() {
     #[cfg(target_os = "windows")]
@@ -1,30 +1,139 @@
-use std::io::{Stdout, Write};
+use std::{
+    io::{Stdout, Write},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use crate::board::{Board, Shot};
+use crate::ai::Strategy;
+use crate::board::{Board, Report, Shot, Strike};
 use crate::cell::Cell;
+use crate::clock::{Minutes, TimeControl, Timeout};
 use crate::error::Error;
+use crate::event::Action;
+use crate::game::{Game, Outcome};
+use crate::rules::Geometry;
+use crate::ship::{Motion, ShipRotation, ShipState};
+use crate::weapon::Weapon;
 use crossterm::{
     cursor::MoveTo,
     event::{KeyCode, KeyModifiers},
     execute, queue,
     style::{Color, Print, PrintStyledContent, Stylize},
 };
+use rand::seq::SliceRandom;
 
 use super::{exit, wait_on_player};
 
+/// Everything the attacker has lined up so far this turn
+pub struct Aim {
+    weapon: Weapon,
+    heading: ShipRotation,
+    targets: Vec<Cell>,
+    /// Picking one of their own ships to move instead of firing
+    moving: bool,
+}
+
+impl Aim {
+    const fn new() -> Self {
+        Self {
+            weapon: Weapon::Shell,
+            heading: ShipRotation::Right,
+            targets: Vec::new(),
+            moving: false,
+        }
+    }
+    /// The cells to mark on the defender's board
+    fn preview(&self, cursor: Cell, geometry: Geometry) -> Vec<Cell> {
+        if self.moving {
+            Vec::new()
+        } else if self.weapon == Weapon::Shell {
+            self.targets.clone()
+        } else {
+            self.weapon.pattern(cursor, self.heading, geometry)
+        }
+    }
+    /// What the attacker is doing, if they have anything but shells to do it with
+    fn status(&self, attacker: &Board) -> String {
+        let rules = attacker.ships.rules();
+        if self.moving {
+            return "Moving: F/B to sail, Q/E to turn the ship under the cursor - M to fire instead"
+                .to_string();
+        }
+        let mut status = String::new();
+        if !rules.arsenal.is_empty() {
+            let weapon = self.weapon;
+            let charges = attacker
+                .arsenal
+                .charges(weapon)
+                .map_or(String::new(), |charges| format!(" ({charges} left)"));
+            let heading = match (weapon, self.heading) {
+                (Weapon::Torpedo, ShipRotation::Left) => " heading left, Q/E to turn",
+                (Weapon::Torpedo, _) => " heading right, Q/E to turn",
+                _ => "",
+            };
+            status = format!("{weapon}{charges}{heading} - Tab to switch weapons");
+        }
+        if rules.moving_ships {
+            if !status.is_empty() {
+                status.push_str(" - ");
+            }
+            status.push_str("M to move a ship");
+        }
+        status
+    }
+}
+
+/// Plays the current player's turn. If `undo` is set they can ask to take
+/// back their last turn instead, in which case this returns `false` and
+/// nothing changes.
 pub fn turn(
     stdout: &mut Stdout,
-    attacker: &mut Board,
-    defender: &mut Board,
+    game: &mut Game,
+    defender: usize,
     cursor: &mut Cell,
-    player: usize,
-) -> Result<(), Error> {
-    crate::ui::show_pass(stdout, player)?;
+    undo: bool,
+) -> Result<bool, Error> {
+    let rules = game.rules().clone();
+    let player = game.current();
+    let ally = game.ally();
+    // never ask for more shots than there are cells left to shoot
+    let shots = rules
+        .salvo
+        .shots(game.board(player))
+        .min(game.board(defender).unfired())
+        .max(1);
+    let mut aim = Aim::new();
     let mut msg = String::with_capacity(128);
-    render_screen(stdout, attacker, defender, cursor, player, "")?;
+    if undo {
+        msg.push_str("U to take back your last turn");
+    }
+    game.clock_mut(player).start();
     loop {
+        super::clear_msgs(stdout)?;
+        render_turn(stdout, game, player, defender, ally, cursor, &aim, &msg)?;
         super::debounce_sleep();
-        let key = crate::util::next_key()?;
+        let (attacker, target) = (game.board(player), game.board(defender));
+        let key = match attacker.clock.remaining() {
+            None => crate::util::next_key()?,
+            Some(left) if left.is_zero() => {
+                game.time_out(player);
+                msg = match rules.timeout {
+                    Timeout::RandomShot => {
+                        format!(
+                            "Out of time! {}",
+                            random_volley(game, defender, &mut aim, shots)
+                        )
+                    }
+                    Timeout::Forfeit => "You ran out of time, and forfeit the game!".to_string(),
+                };
+                break;
+            }
+            // wake up every second or so to redraw the clock
+            Some(left) => match crate::util::next_key_within(left.min(Duration::from_secs(1)))? {
+                Some(key) => key,
+                None => continue,
+            },
+        };
         if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
             exit();
         }
@@ -36,68 +145,398 @@ pub fn turn(
             KeyCode::Right => *cursor += (1, 0),
             KeyCode::Up => *cursor -= (0, 1),
             KeyCode::Down => *cursor += (0, 1),
-            KeyCode::Char(' ') => {
-                if let Some(shot) = defender.fire(cursor) {
-                    msg = match shot {
-                        Shot::Hit(ship) => {
-                            let mut verb = "sunk";
-                            for cell in ship.occupies() {
-                                if !matches!(defender.shot(&cell), Shot::Hit(_ship)) {
-                                    verb = "hit";
-                                    break;
-                                }
-                            }
-                            format!("You {verb} their {}!", ship.kind())
-                        }
-                        Shot::Miss => "You missed.".to_string(),
-                        Shot::Empty => "Shot is empty!?".to_string(),
-                    };
+            KeyCode::Char('u' | 'U') if undo => {
+                game.clock_mut(player).stop();
+                return Ok(false);
+            }
+            KeyCode::Char('g' | 'G') => {
+                if let Some(typed) = super::prompt(stdout, "Go to: ")? {
+                    match typed.parse() {
+                        Ok(cell) => *cursor = cell,
+                        Err(e) => msg = Error::InvalidCell(e).to_string(),
+                    }
+                }
+            }
+            KeyCode::Char('m' | 'M') if rules.moving_ships => {
+                aim.moving = !aim.moving;
+                aim.targets.clear();
+            }
+            KeyCode::Char('f' | 'F' | 'b' | 'B' | 'q' | 'Q' | 'e' | 'E') if aim.moving => {
+                let motion = match key.code {
+                    KeyCode::Char('f' | 'F') => Motion::Forward,
+                    KeyCode::Char('b' | 'B') => Motion::Backward,
+                    KeyCode::Char('q' | 'Q') => Motion::TurnLeft,
+                    _ => Motion::TurnRight,
+                };
+                let Some(ship) = attacker.ships.index_in(*cursor) else {
+                    msg = "There's no ship of yours there.".to_string();
+                    continue;
+                };
+                let kind = attacker.ships.fleet().ships()[ship];
+                if game.act(defender, Action::Move { ship, motion }).is_some() {
+                    msg = format!("You moved your {kind}.");
                     break;
                 }
-                msg = "You already shot there!".to_string();
-                super::clear_msgs(stdout)?;
+                msg = "That ship can't move like that.".to_string();
+            }
+            _ if aim.moving => {}
+            KeyCode::Tab if !attacker.arsenal.is_empty() => {
+                aim.targets.clear();
+                aim.weapon.next();
+                while attacker.arsenal.charges(aim.weapon) == Some(0) {
+                    aim.weapon.next();
+                }
+            }
+            KeyCode::Char('q' | 'Q' | 'e' | 'E') if aim.weapon == Weapon::Torpedo => {
+                aim.heading = if aim.heading == ShipRotation::Right {
+                    ShipRotation::Left
+                } else {
+                    ShipRotation::Right
+                };
+            }
+            KeyCode::Char(' ') if aim.weapon != Weapon::Shell => {
+                let action = Action::Strike {
+                    weapon: aim.weapon,
+                    target: *cursor,
+                    heading: aim.heading,
+                };
+                if let Some(Outcome::Strike(strike)) = game.act(defender, action) {
+                    msg = strike_message(game.board(defender), aim.weapon, &strike);
+                    break;
+                }
+                msg = "There's nothing left to hit there!".to_string();
+            }
+            KeyCode::Char(' ') => {
+                if rules.terrain.is_land(*cursor) {
+                    msg = "You can't shoot at dry land!".to_string();
+                    continue;
+                }
+                if target.shot(cursor) != Shot::Empty {
+                    msg = "You already shot there!".to_string();
+                    continue;
+                }
+                if let Some(picked) = aim.targets.iter().position(|v| v == cursor) {
+                    aim.targets.remove(picked);
+                } else {
+                    aim.targets.push(*cursor);
+                }
+                if aim.targets.len() == shots {
+                    let action = Action::Volley(aim.targets.clone());
+                    if let Some(Outcome::Volley(outcome)) = game.act(defender, action) {
+                        msg = volley_message(game.board(defender), &outcome);
+                        break;
+                    }
+                }
+                if shots > 1 {
+                    msg = format!("{} of {shots} targets picked", aim.targets.len());
+                }
             }
             _ => {}
         }
-        super::clear_msgs(stdout)?;
-        render_screen(stdout, attacker, defender, cursor, player, &msg)?;
     }
-    render_screen(stdout, attacker, defender, cursor, player, &msg)?;
+    game.clock_mut(player).stop();
+    super::clear_msgs(stdout)?;
+    aim = Aim::new();
+    render_turn(stdout, game, player, defender, ally, cursor, &aim, &msg)?;
     execute!(stdout, MoveTo(0, 0))?;
     *cursor = Cell::new(0, 0);
     wait_on_player()?;
+    Ok(true)
+}
+
+/// Pauses in a computer's turn, so whoever's watching can keep up
+const THINKING: Duration = Duration::from_millis(700);
+const BETWEEN_SHOTS: Duration = Duration::from_millis(300);
+const AFTER_TURN: Duration = Duration::from_millis(1200);
+
+/// Lets `strategy` take the current player's turn, shown to `watcher`
+pub fn computer_turn(
+    stdout: &mut Stdout,
+    game: &mut Game,
+    strategy: &mut dyn Strategy,
+    watcher: usize,
+) -> Result<(), Error> {
+    let player = game.current();
+    let name = format!("Player {}", player + 1);
+    // go after whoever's closest to going down
+    let Some(defender) = game
+        .opponents()
+        .into_iter()
+        .min_by_key(|opponent| game.board(*opponent).afloat())
+    else {
+        return Ok(());
+    };
+    let shots = game
+        .rules()
+        .salvo
+        .shots(game.board(player))
+        .min(game.board(defender).unfired())
+        .max(1);
+    game.clock_mut(player).start();
+    let watch = |stdout: &mut Stdout, game: &Game, targets: &[Cell], message: &str| {
+        render_watch(stdout, game, defender, watcher, targets, message)
+    };
+    watch(stdout, game, &[], &format!("{name} is thinking..."))?;
+    // a bot in another program takes its time answering, which counts
+    // towards the pause rather than going on top of it
+    let thinking = Instant::now();
+    let cells = strategy.aim(defender, game.board(defender), shots);
+    std::thread::sleep(THINKING.saturating_sub(thinking.elapsed()));
+    for aimed in 1..=cells.len() {
+        watch(
+            stdout,
+            game,
+            &cells[..aimed],
+            &format!("{name} takes aim..."),
+        )?;
+        std::thread::sleep(BETWEEN_SHOTS);
+    }
+    let outcome = game.act(defender, Action::Volley(cells));
+    game.clock_mut(player).stop();
+    let msg = match outcome {
+        Some(Outcome::Volley(outcome)) => {
+            let whose = if defender == watcher {
+                "your".to_string()
+            } else {
+                format!("player {}'s", defender + 1)
+            };
+            computer_message(&name, &whose, game.board(defender), &outcome)
+        }
+        // it couldn't come up with anything, so it's not going to
+        _ => {
+            game.resign(player);
+            match strategy.trouble() {
+                Some(trouble) => format!("{name} forfeits: {trouble}"),
+                None => format!("{name} gives up!"),
+            }
+        }
+    };
+    watch(stdout, game, &[], &msg)?;
+    std::thread::sleep(AFTER_TURN);
+    Ok(())
+}
+
+/// The board a computer is firing at, with its ships showing if it's the
+/// watcher's own
+fn render_watch(
+    stdout: &mut Stdout,
+    game: &Game,
+    defender: usize,
+    watcher: usize,
+    targets: &[Cell],
+    message: &str,
+) -> Result<(), Error> {
+    super::clear_msgs(stdout)?;
+    draw_board(
+        stdout,
+        game.board(defender),
+        defender == watcher,
+        targets,
+        0,
+    )?;
+    if defender != watcher {
+        draw_board(stdout, game.board(watcher), true, &[], 30)?;
+    }
+    queue!(
+        stdout,
+        MoveTo(0, 0),
+        Print(game.current() + 1),
+        MoveTo(0, 13),
+        Print(message),
+    )?;
+    if let Some(cell) = targets.last() {
+        #[allow(clippy::cast_possible_truncation)]
+        queue!(stdout, MoveTo(cell.x() as u16 * 2 + 2, cell.y() as u16 + 1))?;
+    }
+    stdout.flush()?;
     Ok(())
 }
 
+/// What a computer's volley did, e.g. `Player 2 missed at C4, hit your
+/// Destroyer at D5.`
+fn computer_message(name: &str, whose: &str, defender: &Board, outcome: &[(Cell, Shot)]) -> String {
+    let shots: Vec<String> = outcome
+        .iter()
+        .map(|(cell, shot)| match defender.report(shot) {
+            Report::Sunk(kind) => format!("sunk {whose} {kind} at {cell}"),
+            Report::Hit(Some(kind)) => format!("hit {whose} {kind} at {cell}"),
+            Report::Hit(None) => format!("hit something at {cell}"),
+            Report::Miss | Report::Nothing => format!("missed at {cell}"),
+        })
+        .collect();
+    format!("{name} {}.", shots.join(", "))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_turn(
+    stdout: &mut Stdout,
+    game: &Game,
+    player: usize,
+    defender: usize,
+    ally: Option<usize>,
+    cursor: &Cell,
+    aim: &Aim,
+    message: &str,
+) -> Result<(), Error> {
+    render_screen(
+        stdout,
+        game.board(player),
+        game.board(defender),
+        ally.map(|ally| game.board(ally)),
+        cursor,
+        player + 1,
+        aim,
+        message,
+    )
+}
+
+/// Tops up whatever shells the attacker had picked with random ones and fires
+fn random_volley(game: &mut Game, defender: usize, aim: &mut Aim, shots: usize) -> String {
+    let mut rng = rand::thread_rng();
+    let mut left: Vec<Cell> = game
+        .board(defender)
+        .targets()
+        .into_iter()
+        .filter(|cell| !aim.targets.contains(cell))
+        .collect();
+    left.shuffle(&mut rng);
+    let missing = shots.saturating_sub(aim.targets.len());
+    aim.targets.extend(left.into_iter().take(missing));
+    match game.act(defender, Action::Volley(aim.targets.clone())) {
+        Some(Outcome::Volley(outcome)) => {
+            format!(
+                "Fired at random. {}",
+                volley_message(game.board(defender), &outcome)
+            )
+        }
+        _ => "There was nothing left to fire at.".to_string(),
+    }
+}
+
+fn strike_message(defender: &Board, weapon: Weapon, strike: &Strike) -> String {
+    match strike {
+        Strike::Radar(0) => "Your radar sweep found nothing.".to_string(),
+        Strike::Radar(found) => format!("Your radar sweep picked up {found} ship cells."),
+        Strike::Fired(fired) => match (weapon, fired.last()) {
+            (Weapon::Torpedo, Some((cell, shot @ Shot::Hit(_)))) => match defender.report(shot) {
+                Report::Sunk(kind) => format!("Your torpedo sunk their {kind} at {cell}!"),
+                Report::Hit(Some(kind)) => format!("Your torpedo hit their {kind} at {cell}!"),
+                _ => format!("Your torpedo hit something at {cell}!"),
+            },
+            (Weapon::Torpedo, _) => "Your torpedo ran off the board.".to_string(),
+            _ => format!("{weapon}! {}", volley_message(defender, fired)),
+        },
+    }
+}
+
+fn shot_message(defender: &Board, cell: Cell, shot: &Shot) -> String {
+    match defender.report(shot) {
+        Report::Sunk(kind) => format!("You sunk their {kind} at {cell}!"),
+        Report::Hit(Some(kind)) => format!("You hit their {kind} at {cell}!"),
+        Report::Hit(None) => format!("You hit something at {cell}!"),
+        Report::Miss => format!("You missed at {cell}."),
+        Report::Nothing => "Shot is empty!?".to_string(),
+    }
+}
+
+/// Sums up a whole volley, naming each ship it hit once
+fn volley_message(defender: &Board, outcome: &[(Cell, Shot)]) -> String {
+    if let [(cell, shot)] = outcome {
+        return shot_message(defender, *cell, shot);
+    }
+    let misses = outcome
+        .iter()
+        .filter(|(_cell, shot)| *shot == Shot::Miss)
+        .count();
+    let mut msg = format!(
+        "Volley of {}: {} hit, {misses} missed.",
+        outcome.len(),
+        outcome.len() - misses
+    );
+    // each ship named once, at the first place it was hit
+    let mut ships: Vec<(Cell, &Arc<ShipState>)> = Vec::with_capacity(outcome.len());
+    for (cell, shot) in outcome {
+        // don't name ships the rules keep secret
+        if defender.report(shot) == Report::Hit(None) {
+            continue;
+        }
+        if let Shot::Hit(ship) = shot {
+            if !ships.iter().any(|(_cell, named)| *named == ship) {
+                ships.push((*cell, ship));
+            }
+        }
+    }
+    for (cell, ship) in ships {
+        msg.push(' ');
+        msg.push_str(&shot_message(defender, cell, &Shot::Hit(ship.clone())));
+    }
+    msg
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn render_screen(
     stdout: &mut Stdout,
-    attacker: &mut Board,
-    defender: &mut Board,
-    cursor: &mut Cell,
+    attacker: &Board,
+    defender: &Board,
+    ally: Option<&Board>,
+    cursor: &Cell,
     player: usize,
+    aim: &Aim,
     message: &str,
 ) -> Result<(), Error> {
-    draw_board(stdout, defender, false, 0)?;
-    draw_board(stdout, attacker, true, 30)?;
+    let targets = aim.preview(*cursor, defender.ships.rules().geometry);
+    draw_board(stdout, defender, false, &targets, 0)?;
+    draw_board(stdout, attacker, true, &[], 30)?;
+    // teammates see each other's fleets and what's been fired at them
+    if let Some(ally) = ally {
+        draw_board(stdout, ally, true, &[], 60)?;
+    }
+    let clock_x = if ally.is_some() { 86 } else { 56 };
+    // when moving ships the cursor is on the attacker's own board
+    let x_offset = if aim.moving { 30 } else { 0 };
     queue!(
         stdout,
         MoveTo(0, 0),
         Print(player),
+        MoveTo(0, 12),
+        Print(aim.status(attacker)),
         MoveTo(0, 13),
         Print(message),
+    )?;
+    // time left goes to the right of the boards
+    if let Some(left) = attacker.clock.remaining() {
+        queue!(
+            stdout,
+            MoveTo(clock_x, 1),
+            Print(format!("Your time:  {}  ", Minutes(left)))
+        )?;
+    }
+    if let (TimeControl::Bank { .. }, Some(left)) =
+        (defender.ships.rules().clock, defender.clock.remaining())
+    {
+        queue!(
+            stdout,
+            MoveTo(clock_x, 2),
+            Print(format!("Their time: {}  ", Minutes(left)))
+        )?;
+    }
+    queue!(
+        stdout,
         #[allow(clippy::cast_possible_truncation)]
-        MoveTo(cursor.x() as u16 * 2 + 2, cursor.y() as u16 + 1)
+        MoveTo(cursor.x() as u16 * 2 + 2 + x_offset, cursor.y() as u16 + 1)
     )?;
     stdout.flush()?;
     Ok(())
 }
 
 const HIT_STR: &str = "><";
+const TARGET_STR: &str = "()";
 
 fn draw_board(
     stdout: &mut Stdout,
-    board: &mut Board,
+    board: &Board,
     show_ships: bool,
+    targets: &[Cell],
     x_offset: u16,
 ) -> Result<(), Error> {
     for x in 1..11 {
@@ -114,8 +553,11 @@ fn draw_board(
         for y in 0..10 {
             queue!(stdout, MoveTo((x + 1) * 2 - 1 + x_offset, y + 1))?;
             let cell = Cell::new(x.into(), y.into());
-            let bg_color = if show_ships && board.ships.contains_ship(cell) {
+            let ship = board.ships.ship_in(cell).filter(|_ship| show_ships);
+            let bg_color = if ship.is_some() {
                 Color::Grey
+            } else if board.ships.rules().terrain.is_land(cell) {
+                Color::DarkGreen
             } else {
                 Color::DarkBlue
             };
@@ -132,7 +574,19 @@ fn draw_board(
                         PrintStyledContent(HIT_STR.with(Color::White).on(bg_color))
                     )
                 }
-                Shot::Empty => queue!(stdout, PrintStyledContent("  ".on(bg_color))),
+                Shot::Empty if targets.contains(&cell) => {
+                    queue!(
+                        stdout,
+                        PrintStyledContent(TARGET_STR.with(Color::Yellow).on(bg_color))
+                    )
+                }
+                Shot::Empty => {
+                    let glyph = super::ship_glyph(ship, cell, board.ships.rules().geometry);
+                    queue!(
+                        stdout,
+                        PrintStyledContent(glyph.with(Color::Black).on(bg_color))
+                    )
+                }
             }?;
         }
     }
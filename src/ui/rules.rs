@@ -0,0 +1,198 @@
+use std::io::{Stdout, Write};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::KeyCode,
+    queue,
+    style::{PrintStyledContent, Stylize},
+    terminal::Clear,
+};
+
+use crate::{rules::Rules, ship::Fleet, terrain::Terrain, weapon::Arsenal, Error};
+
+use super::exit;
+
+struct Setting {
+    name: &'static str,
+    value: fn(&Rules) -> String,
+    /// Steps the setting forwards, or backwards if the flag is `false`
+    cycle: fn(&mut Rules, bool),
+}
+
+const SETTINGS: &[Setting] = &[
+    Setting {
+        name: "Players",
+        value: |rules| rules.mode.to_string(),
+        cycle: |rules, forward| {
+            if forward {
+                rules.mode.next();
+            } else {
+                rules.mode.prev();
+            }
+        },
+    },
+    Setting {
+        name: "Fleet",
+        value: |rules| {
+            if rules.fleet == Fleet::classic() {
+                "Classic".to_string()
+            } else if rules.fleet == Fleet::milton_bradley() {
+                "Milton Bradley".to_string()
+            } else if rules.fleet == Fleet::shapes() {
+                "Odd shapes".to_string()
+            } else {
+                "Custom".to_string()
+            }
+        },
+        cycle: |rules, forward| {
+            let presets = [Fleet::classic(), Fleet::milton_bradley(), Fleet::shapes()];
+            let current = presets.iter().position(|fleet| *fleet == rules.fleet);
+            let next = match (current, forward) {
+                (Some(i), true) => (i + 1) % presets.len(),
+                (Some(i), false) => (i + presets.len() - 1) % presets.len(),
+                (None, _) => 0,
+            };
+            rules.fleet = presets[next].clone();
+        },
+    },
+    Setting {
+        name: "Salvo",
+        value: |rules| rules.salvo.to_string(),
+        cycle: |rules, forward| {
+            if forward {
+                rules.salvo.next();
+            } else {
+                rules.salvo.prev();
+            }
+        },
+    },
+    Setting {
+        name: "Placement",
+        value: |rules| rules.placement.to_string(),
+        cycle: |rules, _forward| rules.placement.next(),
+    },
+    Setting {
+        name: "Board",
+        value: |rules| rules.geometry.to_string(),
+        cycle: |rules, _forward| rules.geometry.next(),
+    },
+    Setting {
+        name: "Weapons",
+        value: |rules| {
+            if rules.arsenal.is_empty() {
+                "Shells only".to_string()
+            } else {
+                "Advanced".to_string()
+            }
+        },
+        cycle: |rules, _forward| {
+            rules.arsenal = if rules.arsenal.is_empty() {
+                Arsenal::ADVANCED
+            } else {
+                Arsenal::default()
+            };
+        },
+    },
+    Setting {
+        name: "Moving ships",
+        value: |rules| {
+            if rules.moving_ships {
+                "On".to_string()
+            } else {
+                "Off".to_string()
+            }
+        },
+        cycle: |rules, _forward| rules.moving_ships = !rules.moving_ships,
+    },
+    Setting {
+        name: "Diagonal ships",
+        value: |rules| {
+            if rules.diagonals {
+                "Allowed".to_string()
+            } else {
+                "Not allowed".to_string()
+            }
+        },
+        cycle: |rules, _forward| rules.diagonals = !rules.diagonals,
+    },
+    Setting {
+        name: "Map",
+        value: |rules| {
+            if let Some(seed) = rules.terrain.seed() {
+                return format!("Random #{seed}");
+            }
+            Terrain::presets()
+                .into_iter()
+                .find(|(_name, terrain)| *terrain == rules.terrain)
+                .map_or("Open sea", |(name, _terrain)| name)
+                .to_string()
+        },
+        cycle: |rules, forward| {
+            // open sea, then the presets, then a freshly generated map
+            let mut maps = vec![Terrain::OPEN];
+            maps.extend(Terrain::presets().into_iter().map(|(_name, map)| map));
+            maps.push(Terrain::generate(rand::random()));
+            let current = maps.iter().position(|map| *map == rules.terrain);
+            let next = match (current, forward) {
+                (Some(i), true) => (i + 1) % maps.len(),
+                (Some(i), false) => (i + maps.len() - 1) % maps.len(),
+                // a generated map is always last
+                (None, true) => 0,
+                (None, false) => maps.len() - 2,
+            };
+            rules.terrain = maps[next];
+        },
+    },
+    Setting {
+        name: "Hits report",
+        value: |rules| rules.disclosure.to_string(),
+        cycle: |rules, _forward| rules.disclosure.next(),
+    },
+    Setting {
+        name: "Clock",
+        value: |rules| rules.clock.to_string(),
+        cycle: |rules, _forward| rules.clock.next(),
+    },
+    Setting {
+        name: "Out of time",
+        value: |rules| rules.timeout.to_string(),
+        cycle: |rules, _forward| rules.timeout.next(),
+    },
+];
+
+/// Lets the players tweak `rules` until they hit space or enter
+pub fn edit_rules(stdout: &mut Stdout, rules: &mut Rules) -> Result<(), Error> {
+    let (term_width, term_height) = crossterm::terminal::size()?;
+    queue!(stdout, Clear(crossterm::terminal::ClearType::All), Hide)?;
+    let mut selected = 0;
+    #[allow(clippy::cast_possible_truncation)]
+    let top = (term_height / 2).saturating_sub(SETTINGS.len() as u16 / 2);
+    loop {
+        for (i, setting) in SETTINGS.iter().enumerate() {
+            let line = format!(" {: >12}: {: <24}", setting.name, (setting.value)(rules));
+            let line = if i == selected {
+                line.on_dark_blue().grey()
+            } else {
+                line.on_grey().dark_blue()
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            queue!(
+                stdout,
+                MoveTo(term_width / 2 - 20, top + i as u16),
+                PrintStyledContent(line)
+            )?;
+        }
+        stdout.flush()?;
+        match crate::util::next_key()?.code {
+            KeyCode::Up => selected = selected.checked_sub(1).unwrap_or(SETTINGS.len() - 1),
+            KeyCode::Down => selected = (selected + 1) % SETTINGS.len(),
+            KeyCode::Right | KeyCode::Char('e' | 'E') => (SETTINGS[selected].cycle)(rules, true),
+            KeyCode::Left | KeyCode::Char('q' | 'Q') => (SETTINGS[selected].cycle)(rules, false),
+            KeyCode::Char(' ') | KeyCode::Enter => break,
+            KeyCode::Esc => exit(),
+            _ => {}
+        }
+    }
+    queue!(stdout, Clear(crossterm::terminal::ClearType::All), Show)?;
+    Ok(())
+}
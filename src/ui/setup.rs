@@ -1,97 +1,301 @@
 use crate::board::Board;
 use crate::cell::Cell;
 use crate::error::Error;
-use crate::ship::{ShipRotation, ShipSetBuilder, ShipState, ShipType};
+use crate::layout;
+use crate::rules::Rules;
+use crate::ship::{ShipRotation, ShipSetBuilder, ShipState};
 use crossterm::{
     cursor::MoveTo,
     event::{KeyCode, KeyModifiers},
-    queue,
+    execute, queue,
     style::{Print, PrintStyledContent, Stylize},
     terminal::Clear,
 };
 use std::io::{Stdout, Write};
 
+/// A ship picked up off the board or out of the dock, following the cursor
+struct Held {
+    index: usize,
+    rot: ShipRotation,
+    mirrored: bool,
+    /// The layout from before it was picked up, to put back on cancel
+    before: ShipSetBuilder,
+}
+
+impl Held {
+    fn state(&self, cursor: Cell, ships: &ShipSetBuilder) -> Option<ShipState> {
+        let kind = ships.fleet().get(self.index)?;
+        Some(ShipState::new(cursor, self.rot, kind).with_mirrored(self.mirrored))
+    }
+}
+
+const HELP: &str = "Space pick up/drop - Tab select - Q/E turn - F flip - X remove - U/Y undo/redo - R random - G go to - I/O import/export - Enter ready";
+
 pub fn do_place(
     stdout: &mut Stdout,
     cursor: &mut Cell,
     player: usize,
     action: &str,
+    rules: &Rules,
 ) -> Result<Board, Error> {
-    let mut ships = ShipSetBuilder::new();
-    let mut ship_rot = ShipRotation::Down;
-    let mut ship = ShipType::AircraftCarrier;
-    let mut last_action_was_place = false;
+    let fleet = &rules.fleet;
+    let mut ships = ShipSetBuilder::new(rules);
+    let mut undo: Vec<ShipSetBuilder> = Vec::new();
+    let mut redo: Vec<ShipSetBuilder> = Vec::new();
+    // the ship highlighted in the sidebar
+    let mut selected = 0;
+    let mut held = (!fleet.is_empty()).then(|| Held {
+        index: 0,
+        rot: ShipRotation::Down,
+        mirrored: false,
+        before: ships.clone(),
+    });
     queue!(
         stdout,
         MoveTo(0, 13),
         Clear(crossterm::terminal::ClearType::CurrentLine)
     )?;
     let mut message = action.to_string();
-    ships.carrier(ShipState::new(*cursor, ship_rot, ShipType::AircraftCarrier));
+    // only straight ships get to turn through the diagonals
+    let diagonals =
+        |ship: usize| rules.diagonals && fleet.get(ship).is_some_and(|kind| kind.shape().is_line());
     execute!(stdout, Clear(crossterm::terminal::ClearType::All))?;
-    draw_ship_picker(stdout, &ships, player, &message, cursor)?;
     loop {
+        let preview = preview(&ships, held.as_ref(), *cursor);
+        if message.is_empty() {
+            if let Some(problem) = preview.problems().first() {
+                message = problem.to_string();
+            }
+        }
+        super::clear_msgs(stdout)?;
+        let holding = held.as_ref().map(|held| held.index);
+        draw_ship_picker(
+            stdout, &preview, holding, selected, player, &message, cursor,
+        )?;
+        message.clear();
         super::debounce_sleep();
-        if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
-            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
-                super::exit();
+        let crossterm::event::Event::Key(key) = crossterm::event::read()? else {
+            continue;
+        };
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            super::exit();
+        }
+        if key.code == KeyCode::Esc {
+            super::exit();
+        }
+        match key.code {
+            KeyCode::Left | KeyCode::Char('A' | 'a') => *cursor -= (1, 0),
+            KeyCode::Right | KeyCode::Char('D' | 'd') => *cursor += (1, 0),
+            KeyCode::Up | KeyCode::Char('W' | 'w') => *cursor -= (0, 1),
+            KeyCode::Down | KeyCode::Char('S' | 's') => *cursor += (0, 1),
+            KeyCode::Char('e' | 'E' | '?' | '/' | 'q' | 'Q' | '>' | '.' | 'f' | 'F') => {
+                let Some(held) = &mut held else {
+                    message = "Pick up a ship first".to_string();
+                    continue;
+                };
+                match key.code {
+                    KeyCode::Char('f' | 'F') => held.mirrored = !held.mirrored,
+                    KeyCode::Char('e' | 'E' | '?' | '/') if diagonals(held.index) => {
+                        held.rot.next_eighth();
+                    }
+                    KeyCode::Char('q' | 'Q' | '>' | '.') if diagonals(held.index) => {
+                        held.rot.prev_eighth();
+                    }
+                    KeyCode::Char('e' | 'E' | '?' | '/') => held.rot.next(),
+                    _ => held.rot.prev(),
+                }
             }
-            if key.code == KeyCode::Esc {
-                super::exit();
+            KeyCode::Tab | KeyCode::BackTab if !fleet.is_empty() => {
+                selected = if key.code == KeyCode::Tab {
+                    (selected + 1) % fleet.len()
+                } else {
+                    selected.checked_sub(1).unwrap_or(fleet.len() - 1)
+                };
+                // swap whatever was in hand for the newly selected ship
+                if let Some(old) = held.take() {
+                    ships = old.before;
+                    held = Some(pick_up(&mut ships, selected, cursor));
+                }
             }
-            match key.code {
-                KeyCode::Left | KeyCode::Char('A' | 'a') => *cursor -= (1, 0),
-                KeyCode::Right | KeyCode::Char('D' | 'd') => *cursor += (1, 0),
-                KeyCode::Up | KeyCode::Char('W' | 'w') => *cursor -= (0, 1),
-                KeyCode::Down | KeyCode::Char('S' | 's') => *cursor += (0, 1),
-                KeyCode::Char('e' | 'E' | '?' | '/') => ship_rot.next(),
-                KeyCode::Char('q' | 'Q' | '>' | '.') => ship_rot.prev(),
-                KeyCode::Char(' ') | KeyCode::Enter => {
-                    if ships.is_valid() && ship.next() {
-                        if let Some(finished) = ships.build() {
-                            *cursor = Cell::new(0, 0);
-                            return Ok(Board::new(finished));
-                        }
-                        super::clear_msgs(stdout)?;
-                        message = "Board is valid but is invalid!?".to_string();
+            KeyCode::Char(' ') => match held.take() {
+                Some(dropped) => {
+                    if !preview.is_valid() {
+                        message = "That ship can't go there".to_string();
+                        held = Some(dropped);
+                        continue;
+                    }
+                    ships = preview;
+                    undo.push(dropped.before);
+                    redo.clear();
+                    // straight on to the next ship still in the dock
+                    if let Some(next) = (0..fleet.len()).find(|i| ships.placed(*i).is_none()) {
+                        selected = next;
+                        held = Some(pick_up(&mut ships, next, cursor));
                     }
-                    last_action_was_place = true;
                 }
-                _ => {}
+                None => {
+                    let index = ships.index_in(*cursor).unwrap_or(selected);
+                    selected = index;
+                    held = Some(pick_up(&mut ships, index, cursor));
+                }
+            },
+            KeyCode::Char('x' | 'X') | KeyCode::Delete | KeyCode::Backspace => {
+                if let Some(removed) = held.take() {
+                    // putting back a ship fresh out of the dock changes nothing
+                    if removed.before != ships {
+                        undo.push(removed.before);
+                        redo.clear();
+                    }
+                } else if let Some(index) = ships.index_in(*cursor) {
+                    undo.push(ships.clone());
+                    redo.clear();
+                    ships.remove(index);
+                    selected = index;
+                } else {
+                    message = "There's no ship there to remove".to_string();
+                }
             }
-        }
-        match ship {
-            ShipType::AircraftCarrier => {
-                ships.carrier(ShipState::new(*cursor, ship_rot, ShipType::AircraftCarrier));
+            KeyCode::Char('u' | 'U' | 'z' | 'Z') => {
+                if let Some(old) = held.take() {
+                    ships = old.before;
+                }
+                match undo.pop() {
+                    Some(prev) => redo.push(std::mem::replace(&mut ships, prev)),
+                    None => message = "Nothing to undo".to_string(),
+                }
             }
-            ShipType::Battleship => {
-                ships.battleship(ShipState::new(*cursor, ship_rot, ShipType::Battleship));
+            KeyCode::Char('y' | 'Y') => {
+                if let Some(old) = held.take() {
+                    ships = old.before;
+                }
+                match redo.pop() {
+                    Some(next) => undo.push(std::mem::replace(&mut ships, next)),
+                    None => message = "Nothing to redo".to_string(),
+                }
             }
-            ShipType::Destroyer => {
-                ships.destroyer(ShipState::new(*cursor, ship_rot, ShipType::Destroyer));
+            KeyCode::Char('r' | 'R') => {
+                if let Some(old) = held.take() {
+                    ships = old.before;
+                }
+                let before = ships.clone();
+                if ships.randomize(rand::random()) {
+                    undo.push(before);
+                    redo.clear();
+                    message = "Fleet randomized - Enter when ready, R to roll again".to_string();
+                } else {
+                    ships = before;
+                    message = "Couldn't fit the fleet anywhere!".to_string();
+                }
             }
-            ShipType::Submarine => {
-                ships.submarine(ShipState::new(*cursor, ship_rot, ShipType::Submarine));
+            KeyCode::Char('g' | 'G') => {
+                if let Some(typed) = super::prompt(stdout, "Go to: ")? {
+                    match typed.parse() {
+                        Ok(cell) => *cursor = cell,
+                        Err(e) => message = Error::InvalidCell(e).to_string(),
+                    }
+                }
             }
-            ShipType::PatrolBoat => {
-                ships.patrol(ShipState::new(*cursor, ship_rot, ShipType::PatrolBoat));
+            KeyCode::Char('i' | 'I') => {
+                let Some(path) = super::prompt(stdout, "Import layout from: ")? else {
+                    continue;
+                };
+                match import(&path, rules) {
+                    Ok(imported) => {
+                        let before = match held.take() {
+                            Some(old) => old.before,
+                            None => ships.clone(),
+                        };
+                        ships = imported;
+                        undo.push(before);
+                        redo.clear();
+                        message = format!("Imported {path}");
+                    }
+                    Err(Error::IoFailed(e)) => message = format!("Couldn't read {path}: {e}"),
+                    Err(e) => message = e.to_string(),
+                }
+            }
+            KeyCode::Char('o' | 'O') => {
+                if held.is_some() {
+                    message = "Drop the ship you're holding first".to_string();
+                    continue;
+                }
+                let finished = match ships.build() {
+                    Ok(finished) => finished,
+                    Err(e) => {
+                        message = e.to_string();
+                        continue;
+                    }
+                };
+                let Some(path) = super::prompt(stdout, "Export layout to: ")? else {
+                    continue;
+                };
+                message = match std::fs::write(&path, layout::to_text(&finished)) {
+                    Ok(()) => format!("Saved layout to {path}"),
+                    Err(e) => format!("Couldn't save to {path}: {e}"),
+                };
+            }
+            KeyCode::Enter => {
+                if held.is_some() {
+                    message = "Drop the ship you're holding first".to_string();
+                    continue;
+                }
+                match ships.build() {
+                    Ok(finished) => {
+                        *cursor = Cell::new(0, 0);
+                        return Ok(Board::new(finished));
+                    }
+                    Err(e) => message = e.to_string(),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn import(path: &str, rules: &Rules) -> Result<ShipSetBuilder, Error> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(layout::parse(&text, rules)?.to_builder())
+}
+
+/// Takes the `index`th ship in hand, off the board if it's on it
+fn pick_up(ships: &mut ShipSetBuilder, index: usize, cursor: &mut Cell) -> Held {
+    let before = ships.clone();
+    match ships.remove(index) {
+        Some(ship) => {
+            // grab it where it lies, so it doesn't jump
+            *cursor = ship.pos();
+            Held {
+                index,
+                rot: ship.rotation(),
+                mirrored: ship.is_mirrored(),
+                before,
             }
         }
-        if !ships.is_valid() && !last_action_was_place {
-            super::clear_msgs(stdout)?;
-            message = "Invalid board layout".to_string();
-        } else if ships.is_valid() {
-            super::clear_msgs(stdout)?;
+        None => Held {
+            index,
+            rot: ShipRotation::Down,
+            mirrored: false,
+            before,
+        },
+    }
+}
+
+/// The layout with the ship in hand dropped at the cursor
+fn preview(ships: &ShipSetBuilder, held: Option<&Held>, cursor: Cell) -> ShipSetBuilder {
+    let mut preview = ships.clone();
+    if let Some(held) = held {
+        if let Some(state) = held.state(cursor, ships) {
+            preview.place(held.index, state);
         }
-        draw_ship_picker(stdout, &ships, player, &message, cursor)?;
-        message.clear();
-        last_action_was_place = false;
     }
+    preview
 }
 
 fn draw_ship_picker(
     stdout: &mut Stdout,
     ships: &ShipSetBuilder,
+    holding: Option<usize>,
+    selected: usize,
     player: usize,
     message: &str,
     cursor: &Cell,
@@ -106,23 +310,47 @@ fn draw_ship_picker(
             Print(char::from_u32('A' as u32 + (u32::from(y) - 1)).unwrap_or('X'))
         )?;
     }
+    let geometry = ships.rules().geometry;
+    let buffer = ships.buffer_cells(holding.unwrap_or(usize::MAX));
+    let problems = ships.problems();
+    let bad: Vec<Cell> = problems
+        .iter()
+        .flat_map(|problem| problem.cells().iter().copied())
+        .collect();
+    // the ship in hand is drawn over anything it's overlapping
+    let held = holding.and_then(|index| ships.placed(index));
+    let held_cells = held.map_or(Vec::new(), |ship| ship.occupies(geometry));
     for x in 0..10 {
         for y in 0..10 {
             let cell = Cell::new(x.into(), y.into());
-            let on_color = if ships.contains_ship(cell) {
+            let ship = if held_cells.contains(&cell) {
+                held
+            } else {
+                ships.ship_in(cell)
+            };
+            let on_color = if bad.contains(&cell) {
+                Stylize::on_red
+            } else if ship.is_some() {
                 Stylize::on_grey
+            } else if ships.rules().terrain.is_land(cell) {
+                Stylize::on_dark_green
+            } else if buffer.contains(&cell) {
+                Stylize::on_dark_blue
             } else {
                 Stylize::on_blue
             };
             queue!(
                 stdout,
                 MoveTo((x + 1) * 2 - 1, y + 1),
-                PrintStyledContent(on_color("  "))
+                PrintStyledContent(on_color(super::ship_glyph(ship, cell, geometry).black()))
             )?;
         }
     }
+    draw_sidebar(stdout, ships, holding, selected, &bad)?;
     queue!(
         stdout,
+        MoveTo(0, 12),
+        Print(HELP),
         MoveTo(0, 13),
         Print(message),
         MoveTo(0, 0),
@@ -133,3 +361,60 @@ fn draw_ship_picker(
     stdout.flush()?;
     Ok(())
 }
+
+/// Every ship of the fleet and how it's doing, next to the board
+fn draw_sidebar(
+    stdout: &mut Stdout,
+    ships: &ShipSetBuilder,
+    holding: Option<usize>,
+    selected: usize,
+    bad: &[Cell],
+) -> Result<(), Error> {
+    const X: u16 = 25;
+    let geometry = ships.rules().geometry;
+    for (index, kind) in ships.fleet().ships().iter().enumerate() {
+        let placed = ships.placed(index);
+        let status = match placed {
+            _ if holding == Some(index) => "in hand",
+            Some(_) => "placed",
+            None => "in dock",
+        };
+        let line = format!(
+            "{} {: <20} {status: <8}",
+            if index == selected { '>' } else { ' ' },
+            kind.name()
+        );
+        let troubled = placed.is_some_and(|ship| {
+            ship.occupies(geometry)
+                .iter()
+                .any(|cell| bad.contains(cell))
+        });
+        let line = if troubled {
+            line.red()
+        } else if holding == Some(index) {
+            line.yellow()
+        } else if placed.is_some() {
+            line.green()
+        } else {
+            line.grey()
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        queue!(
+            stdout,
+            MoveTo(X, index as u16 + 1),
+            PrintStyledContent(line)
+        )?;
+    }
+    let ready = holding.is_none() && ships.is_complete() && ships.is_valid();
+    let ready_line = " Ready (Enter) ";
+    queue!(
+        stdout,
+        MoveTo(X, 0),
+        PrintStyledContent(if ready {
+            ready_line.on_dark_green().white()
+        } else {
+            ready_line.on_grey().dark_grey()
+        })
+    )?;
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use crossterm::event::KeyEvent;
 
 use crate::Error;
@@ -9,3 +11,17 @@ pub fn next_key() -> Result<KeyEvent, Error> {
         }
     }
 }
+
+/// Like [`next_key`], but gives up after `timeout`
+pub fn next_key_within(timeout: Duration) -> Result<Option<KeyEvent>, Error> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if !crossterm::event::poll(left)? {
+            return Ok(None);
+        }
+        if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
+            return Ok(Some(key));
+        }
+    }
+}
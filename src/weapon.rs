@@ -0,0 +1,177 @@
+use crate::{cell::Cell, rules::Geometry, ship::ShipRotation};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Weapon {
+    /// The plain old single shot
+    Shell,
+    /// Counts the ship cells in a 3x3 area without firing
+    Radar,
+    /// Runs along a row until it hits a ship
+    Torpedo,
+    /// Fires at a cell and the four cells next to it
+    Airstrike,
+}
+
+impl Weapon {
+    pub fn next(&mut self) {
+        *self = match self {
+            Self::Shell => Self::Radar,
+            Self::Radar => Self::Torpedo,
+            Self::Torpedo => Self::Airstrike,
+            Self::Airstrike => Self::Shell,
+        };
+    }
+    /// The cells this weapon covers when aimed at `target`. Torpedoes run
+    /// towards `heading`, and list their cells in the order they pass them.
+    pub fn pattern(self, target: Cell, heading: ShipRotation, geometry: Geometry) -> Vec<Cell> {
+        let offsets: Vec<(isize, isize)> = match self {
+            Self::Shell => vec![(0, 0)],
+            Self::Radar => (-1..=1)
+                .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+                .collect(),
+            Self::Torpedo => {
+                let (dx, dy) = heading.step();
+                (0..10).map(|i| (dx * i, dy * i)).collect()
+            }
+            Self::Airstrike => vec![(0, 0), (0, -1), (1, 0), (0, 1), (-1, 0)],
+        };
+        let cells = offsets
+            .into_iter()
+            .map(|(dx, dy)| geometry.offset(target, dx, dy));
+        if self == Self::Torpedo {
+            // a torpedo that runs off the board doesn't come back
+            cells.map_while(|cell| cell).collect()
+        } else {
+            cells.flatten().collect()
+        }
+    }
+}
+
+impl std::fmt::Display for Weapon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Shell => "Shell",
+            Self::Radar => "Radar sweep",
+            Self::Torpedo => "Torpedo",
+            Self::Airstrike => "Airstrike",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How many times a player can use each special weapon.
+/// Shells are unlimited.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Arsenal {
+    radar: u8,
+    torpedo: u8,
+    airstrike: u8,
+}
+
+impl Arsenal {
+    pub const ADVANCED: Self = Self::new(2, 1, 1);
+
+    pub const fn new(radar: u8, torpedo: u8, airstrike: u8) -> Self {
+        Self {
+            radar,
+            torpedo,
+            airstrike,
+        }
+    }
+    /// Charges left for `weapon`, or `None` if it never runs out
+    pub const fn charges(&self, weapon: Weapon) -> Option<u8> {
+        match weapon {
+            Weapon::Shell => None,
+            Weapon::Radar => Some(self.radar),
+            Weapon::Torpedo => Some(self.torpedo),
+            Weapon::Airstrike => Some(self.airstrike),
+        }
+    }
+    /// Uses up one charge of `weapon`. Returns `false` if there were none left.
+    pub fn take(&mut self, weapon: Weapon) -> bool {
+        let charges = match weapon {
+            Weapon::Shell => return true,
+            Weapon::Radar => &mut self.radar,
+            Weapon::Torpedo => &mut self.torpedo,
+            Weapon::Airstrike => &mut self.airstrike,
+        };
+        if *charges == 0 {
+            return false;
+        }
+        *charges -= 1;
+        true
+    }
+    /// Returns `true` if there are no special weapons at all
+    pub const fn is_empty(&self) -> bool {
+        self.radar == 0 && self.torpedo == 0 && self.airstrike == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(cells: &[&str]) -> Vec<Cell> {
+        cells.iter().map(|cell| cell.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn radar_in_a_corner() {
+        let pattern = Weapon::Radar.pattern(Cell::new(0, 0), ShipRotation::Right, Geometry::Flat);
+        assert_eq!(pattern.len(), 4);
+        for cell in cells(&["A1", "A2", "B1", "B2"]) {
+            assert!(pattern.contains(&cell), "{cell} missing from {pattern:?}");
+        }
+        let pattern = Weapon::Radar.pattern(Cell::new(9, 9), ShipRotation::Right, Geometry::Flat);
+        assert_eq!(pattern.len(), 4);
+    }
+
+    #[test]
+    fn radar_in_a_corner_wraps_on_a_torus() {
+        let pattern = Weapon::Radar.pattern(Cell::new(0, 0), ShipRotation::Right, Geometry::Torus);
+        assert_eq!(pattern.len(), 9);
+        for cell in cells(&["J10", "J1", "A10", "B2"]) {
+            assert!(pattern.contains(&cell), "{cell} missing from {pattern:?}");
+        }
+    }
+
+    #[test]
+    fn radar_on_an_edge() {
+        let pattern = Weapon::Radar.pattern(Cell::new(4, 0), ShipRotation::Right, Geometry::Flat);
+        assert_eq!(pattern.len(), 6);
+        let pattern = Weapon::Radar.pattern(Cell::new(0, 4), ShipRotation::Right, Geometry::Flat);
+        assert_eq!(pattern.len(), 6);
+    }
+
+    #[test]
+    fn airstrike_on_the_top_row() {
+        let pattern =
+            Weapon::Airstrike.pattern(Cell::new(4, 0), ShipRotation::Right, Geometry::Flat);
+        assert_eq!(pattern, cells(&["A5", "A6", "B5", "A4"]));
+        let pattern =
+            Weapon::Airstrike.pattern(Cell::new(4, 0), ShipRotation::Right, Geometry::Torus);
+        assert_eq!(pattern, cells(&["A5", "J5", "A6", "B5", "A4"]));
+    }
+
+    #[test]
+    fn airstrike_in_a_corner() {
+        let pattern =
+            Weapon::Airstrike.pattern(Cell::new(9, 9), ShipRotation::Right, Geometry::Flat);
+        assert_eq!(pattern, cells(&["J10", "I10", "J9"]));
+        let pattern =
+            Weapon::Airstrike.pattern(Cell::new(9, 9), ShipRotation::Right, Geometry::Torus);
+        assert_eq!(pattern, cells(&["J10", "I10", "J1", "A10", "J9"]));
+    }
+
+    #[test]
+    fn torpedo_stops_at_the_edge() {
+        let pattern = Weapon::Torpedo.pattern(Cell::new(7, 2), ShipRotation::Right, Geometry::Flat);
+        assert_eq!(pattern, cells(&["C8", "C9", "C10"]));
+        let pattern =
+            Weapon::Torpedo.pattern(Cell::new(7, 2), ShipRotation::Right, Geometry::Torus);
+        assert_eq!(pattern.len(), 10);
+        assert_eq!(pattern[3], Cell::new(0, 2));
+    }
+}